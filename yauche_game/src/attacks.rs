@@ -0,0 +1,110 @@
+use crate::board::{Board, PieceColor, PieceType, Squares, SIZE};
+use crate::moves::Move;
+
+/// How many pieces of each colour attack every square. A piece is defended by
+/// the attacks of its own colour on its square.
+///
+/// Attacks are counted as in [`Board::attackers`]: pinned pieces attack all the
+/// same, and a slider behind another piece on the same line isn't counted.
+///
+/// ```
+/// use yauche_game::{Board, PieceColor};
+///
+/// let map = Board::default().attack_map();
+/// // f3 is covered by the pawns on e2 and g2 and the knight on g1
+/// assert_eq!(map.attacks(46, &PieceColor::White), 3);
+/// assert_eq!(map.attacks(46, &PieceColor::Black), 0);
+/// // The pawn on e2 is defended by the king, queen, bishop and knight
+/// assert_eq!(map.attacks(35, &PieceColor::White), 4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttackMap {
+    counts: [[u8; SIZE]; 2],
+}
+
+impl AttackMap {
+    /// Number of pieces of `color` attacking `square`.
+    pub fn attacks(&self, square: usize, color: &PieceColor) -> u8 {
+        self.counts[color.list_index()][square]
+    }
+
+    /// Whether `color` attacks `square` more often than the other side does.
+    pub fn controls(&self, square: usize, color: &PieceColor) -> bool {
+        self.attacks(square, color) > self.attacks(square, &color.opposite())
+    }
+}
+
+impl Board {
+    /// Attack counts of both colours on every square of the playing area.
+    pub fn attack_map(&self) -> AttackMap {
+        let mut counts = [[0; SIZE]; 2];
+        for square in (21..99).filter(|&square| Board::is_playing_square(square)) {
+            for color in [PieceColor::White, PieceColor::Black] {
+                let count = &mut counts[color.list_index()][square];
+                self.find_attackers(square, &color, |_| {
+                    *count += 1;
+                    false
+                });
+            }
+        }
+        AttackMap { counts }
+    }
+
+    /// Pieces of `color` other than the king that hang: attacked and not
+    /// defended, or attacked by a piece worth less than themselves.
+    ///
+    /// ```
+    /// use yauche_game::{PieceColor, Position};
+    ///
+    /// // The knight on c6 is defended but attacked by the pawn on d5,
+    /// // the bishop on g4 is attacked by the pawn on f3
+    /// let fen = "r2qkbnr/ppp1pppp/2n5/3P4/6b1/5P2/PPPP2PP/RNBQKBNR b KQkq - 0 4";
+    /// let position = Position::from_fen(fen).unwrap();
+    /// assert_eq!(position.board.hanging_pieces(&PieceColor::Black), [57, 73]);
+    /// ```
+    pub fn hanging_pieces(&self, color: &PieceColor) -> Vec<usize> {
+        let kings = self.piece_mask(color, PieceType::King(false));
+        Squares(self.color_mask(color) & !kings)
+            .filter(|&square| {
+                let value = self.fields()[square].map_or(0, |p| p.piece_type.value());
+                let cheapest = self
+                    .attackers(square, &color.opposite())
+                    .into_iter()
+                    .filter_map(|attacker| self.fields()[attacker])
+                    .map(|attacker| attacker.piece_type.value())
+                    .min();
+                match cheapest {
+                    None => false,
+                    Some(cheapest) => cheapest < value || !self.is_attacked(square, color),
+                }
+            })
+            .collect()
+    }
+
+    /// Enemy pieces, the king included, that the side playing `mv` attacks once
+    /// it is played but doesn't attack now. Discovered attacks count too.
+    /// `mv` has to come from the move generator.
+    ///
+    /// ```
+    /// use yauche_game::Position;
+    ///
+    /// // Nc7+ forks the king on e8 and the rook on a8
+    /// let fen = "r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1";
+    /// let position = Position::from_fen(fen).unwrap();
+    /// let fork = position.parse_move("Nc7+").unwrap();
+    /// assert_eq!(position.board.new_attacks(&fork), [91, 95]);
+    /// ```
+    pub fn new_attacks(&self, mv: &Move) -> Vec<usize> {
+        let Some(mover) = self.fields()[mv.source] else {
+            return Vec::new();
+        };
+        let color = mover.piece_color;
+        let mut after = *self;
+        after.move_pieces(mv);
+        Squares(after.color_mask(&color.opposite()))
+            .filter(|&square| {
+                after.is_attacked(square, &color) && !self.is_attacked(square, &color)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,289 @@
+use crate::board::{Board, PieceColor, PieceType};
+use crate::error::Error;
+use crate::fen::set_up_piece;
+use crate::packed::{index, mailbox};
+use crate::position::Position;
+
+/// Format written by [`Position::to_bytes`], the first byte of its output.
+pub const BINARY_VERSION: u8 = 1;
+
+// Piece codes, with 8 added for Black. A rook that can still castle and a pawn
+// that just made a double step have their own codes, so that castling rights
+// and en passant need no further bits.
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn(true),
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook(true),
+    PieceType::Queen,
+    PieceType::King(true),
+];
+const CASTLING_ROOK: u8 = 6;
+const EN_PASSANT_PAWN: u8 = 7;
+const BLACK: u8 = 8;
+
+// Rook squares of the castling rights, in the order of Board::castling_rights
+const CASTLING_ROOKS: [usize; 4] = [28, 21, 98, 91];
+
+impl Position {
+    /// Writes the position in a compact binary form holding what FEN holds:
+    ///
+    /// - the version byte [`BINARY_VERSION`]
+    /// - a bitmap of the occupied squares, 8 bytes little-endian with `a1` as
+    ///   the lowest bit
+    /// - a piece code of 4 bits per occupied square, in the order of the bitmap
+    ///   and the low half of each byte first. Rooks that can still castle and
+    ///   a pawn that can be taken en passant get codes of their own.
+    /// - a byte with the side to move, 0 for White and 1 for Black
+    /// - the halfmove clock and the fullmove number, as LEB128 numbers
+    ///
+    /// The starting position takes 28 bytes.
+    ///
+    /// ```
+    /// use yauche_game::Position;
+    ///
+    /// let mut position = Position::default();
+    /// let e4 = position.parse_move("e4").unwrap();
+    /// position.make_move(&e4);
+    /// let bytes = position.to_bytes();
+    /// assert_eq!(bytes.len(), 28);
+    /// assert_eq!(Position::from_bytes(&bytes).unwrap().to_fen(), position.to_fen());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let rights = self.board.castling_rights();
+        let pushed = self
+            .board
+            .en_passant(&self.log)
+            .and_then(|_| self.log.last())
+            .map(|last| last.target);
+
+        let mut occupied = 0u64;
+        let mut codes = Vec::with_capacity(32);
+        for square in (21..99).filter(|&square| Board::is_playing_square(square)) {
+            let Some(piece) = self.board.fields()[square] else {
+                continue;
+            };
+            occupied |= 1 << index(square);
+            let code = if Some(square) == pushed {
+                EN_PASSANT_PAWN
+            } else if CASTLING_ROOKS
+                .iter()
+                .zip(rights)
+                .any(|(&rook, allowed)| allowed && rook == square)
+            {
+                CASTLING_ROOK
+            } else {
+                PIECE_TYPES
+                    .iter()
+                    .position(|piece_type| {
+                        std::mem::discriminant(piece_type)
+                            == std::mem::discriminant(&piece.piece_type)
+                    })
+                    .unwrap_or_default() as u8
+            };
+            codes.push(match piece.piece_color {
+                PieceColor::White => code,
+                PieceColor::Black => code | BLACK,
+            });
+        }
+
+        let mut bytes = vec![BINARY_VERSION];
+        bytes.extend_from_slice(&occupied.to_le_bytes());
+        bytes.extend(
+            codes
+                .chunks(2)
+                .map(|pair| pair[0] | pair.get(1).map_or(0, |code| code << 4)),
+        );
+        bytes.push(match self.side {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        });
+        write_number(&mut bytes, self.halfmove_clock);
+        write_number(&mut bytes, self.fullmove_number);
+        bytes
+    }
+
+    /// Reads a position written by [`Position::to_bytes`], with the same checks
+    /// as [`Position::from_fen`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidBytes(reason.to_string());
+        let (&version, rest) = bytes.split_first().ok_or_else(|| invalid("no bytes"))?;
+        if version != BINARY_VERSION {
+            return Err(Error::InvalidBytes(format!("unknown version {}", version)));
+        }
+        let (occupied, rest) = rest
+            .split_first_chunk::<8>()
+            .ok_or_else(|| invalid("bitmap cut short"))?;
+        let occupied = u64::from_le_bytes(*occupied);
+        let count = occupied.count_ones() as usize;
+        if rest.len() < count.div_ceil(2) {
+            return Err(invalid("pieces cut short"));
+        }
+        let (codes, rest) = rest.split_at(count.div_ceil(2));
+
+        let mut board = Board::empty();
+        let mut castling = [false; 4];
+        let mut en_passant = None;
+        for (n, bit) in (0..64).filter(|bit| occupied & 1 << bit != 0).enumerate() {
+            let square = mailbox(bit);
+            let code = (codes[n / 2] >> (n % 2 * 4)) & 0xF;
+            let color = if code & BLACK == 0 {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            let piece_type = match code & !BLACK {
+                CASTLING_ROOK => {
+                    let right = CASTLING_ROOKS
+                        .iter()
+                        .position(|&rook| rook == square)
+                        .ok_or_else(|| invalid("castling rook off its home square"))?;
+                    castling[right] = true;
+                    PieceType::Rook(true)
+                }
+                EN_PASSANT_PAWN => {
+                    if en_passant.is_some() {
+                        return Err(invalid("two pawns to take en passant"));
+                    }
+                    // The square the pawn passed over
+                    en_passant = Some(match color {
+                        PieceColor::White => square - 10,
+                        PieceColor::Black => square + 10,
+                    });
+                    PieceType::Pawn(true)
+                }
+                code => PIECE_TYPES[code as usize],
+            };
+            board.set(square, Some(set_up_piece(piece_type, color, square)?));
+        }
+        if count % 2 == 1 && codes[count / 2] >> 4 != 0 {
+            return Err(invalid("padding after the last piece"));
+        }
+
+        let (&side, mut rest) = rest
+            .split_first()
+            .ok_or_else(|| invalid("no side to move"))?;
+        let side = match side {
+            0 => PieceColor::White,
+            1 => PieceColor::Black,
+            _ => return Err(invalid("side to move isn't 0 or 1")),
+        };
+        let halfmove_clock = read_number(&mut rest).ok_or_else(|| invalid("bad halfmove clock"))?;
+        let fullmove_number =
+            read_number(&mut rest).ok_or_else(|| invalid("bad fullmove number"))?;
+        if !rest.is_empty() {
+            return Err(invalid("bytes after the position"));
+        }
+        Ok(Self::set_up(
+            board,
+            side,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        )?)
+    }
+}
+
+// Seven bits at a time, the lowest first, the top bit set on all bytes but
+// the last
+fn write_number(bytes: &mut Vec<u8>, mut number: u32) {
+    while number >= 0x80 {
+        bytes.push(number as u8 | 0x80);
+        number >>= 7;
+    }
+    bytes.push(number as u8);
+}
+
+fn read_number(bytes: &mut &[u8]) -> Option<u32> {
+    let mut number = 0u64;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        number |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return u32::try_from(number).ok();
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(fen: &str) -> Vec<u8> {
+        let position = Position::from_fen(fen).unwrap();
+        let bytes = position.to_bytes();
+        assert_eq!(Position::from_bytes(&bytes).unwrap().to_fen(), fen);
+        bytes
+    }
+
+    fn invalid(bytes: &[u8], reason: &str) {
+        assert_eq!(
+            Position::from_bytes(bytes).unwrap_err(),
+            Error::InvalidBytes(reason.to_string())
+        );
+    }
+
+    #[test]
+    fn castling_rooks() {
+        let bytes = round_trip("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1");
+        // a1 e1, h1 a8, e8 h8: only the rooks with a right get the castling code
+        assert_eq!(
+            bytes[9..12],
+            [
+                3 | 5 << 4,
+                CASTLING_ROOK | (CASTLING_ROOK | BLACK) << 4,
+                13 | 11 << 4
+            ]
+        );
+    }
+
+    #[test]
+    fn en_passant_pawn() {
+        let bytes = round_trip("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        // e1 d5, e5 e8
+        assert_eq!(bytes[9..11], [5 | (EN_PASSANT_PAWN | BLACK) << 4, 13 << 4]);
+        let position = Position::from_bytes(&bytes).unwrap();
+        assert!(position.parse_move("exd6").is_ok());
+    }
+
+    #[test]
+    fn large_counters() {
+        round_trip("4k3/8/8/8/8/8/8/4K3 b - - 99 300");
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let mut bytes = Position::default().to_bytes();
+        bytes[0] = BINARY_VERSION + 1;
+        invalid(&bytes, &format!("unknown version {}", BINARY_VERSION + 1));
+        invalid(&[], "no bytes");
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = Position::default().to_bytes();
+        invalid(&bytes[..5], "bitmap cut short");
+        invalid(&bytes[..20], "pieces cut short");
+        invalid(&bytes[..25], "no side to move");
+        invalid(&bytes[..26], "bad halfmove clock");
+    }
+
+    #[test]
+    fn padding() {
+        let mut bytes = round_trip("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        // Three pieces, the last code fills the low half of the second byte
+        assert_eq!(bytes[10] >> 4, 0);
+        bytes[10] |= 1 << 4;
+        invalid(&bytes, "padding after the last piece");
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let mut bytes = Position::default().to_bytes();
+        bytes.push(0);
+        invalid(&bytes, "bytes after the position");
+    }
+}
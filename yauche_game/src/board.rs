@@ -1,3 +1,6 @@
+use crate::error::Error;
+use crate::render::Renderer;
+use crate::zobrist::piece_key;
 use std::fmt;
 
 pub const COLUMNS: usize = 10;
@@ -15,37 +18,101 @@ pub enum PieceType {
     King(bool),
 }
 
+impl PieceType {
+    /// Material value in centipawns, as used by the exchange evaluator.
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::Pawn(_) => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook(_) => 500,
+            PieceType::Queen => 900,
+            PieceType::King(_) => 20000,
+        }
+    }
+
+    // Position in the piece lists, whatever the moved flag
+    const fn list_index(&self) -> usize {
+        match self {
+            PieceType::Pawn(_) => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook(_) => 3,
+            PieceType::Queen => 4,
+            PieceType::King(_) => 5,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PieceColor {
     Black,
     White,
 }
 
+impl PieceColor {
+    pub fn opposite(&self) -> Self {
+        match self {
+            PieceColor::Black => PieceColor::White,
+            PieceColor::White => PieceColor::Black,
+        }
+    }
+
+    /// 0 for White and 1 for Black, to index tables kept per colour.
+    pub(crate) const fn list_index(&self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Piece {
     pub piece_type: PieceType,
     pub piece_color: PieceColor,
 }
 impl Piece {
-    pub fn new(name: &str, color: PieceColor) -> Self {
+    /// Piece called `name`, e.g. `"knight"` or `"Knight"`. Kings, rooks and pawns
+    /// start out as not moved yet.
+    ///
+    /// ```
+    /// use yauche_game::{Error, Piece, PieceColor, PieceType};
+    ///
+    /// let rook = Piece::new("Rook", PieceColor::Black).unwrap();
+    /// assert_eq!(rook.piece_type, PieceType::Rook(false));
+    /// assert_eq!(
+    ///     Piece::new("rock", PieceColor::Black),
+    ///     Err(Error::InvalidPiece("rock".to_string()))
+    /// );
+    /// ```
+    pub fn new(name: &str, color: PieceColor) -> Result<Self, Error> {
+        let piece_type = match name.to_ascii_lowercase().as_str() {
+            "king" => PieceType::King(false),
+            "knight" => PieceType::Knight,
+            "rook" => PieceType::Rook(false),
+            "bishop" => PieceType::Bishop,
+            "queen" => PieceType::Queen,
+            "pawn" => PieceType::Pawn(false),
+            _ => return Err(Error::InvalidPiece(name.to_string())),
+        };
+        Ok(Self::from_type(piece_type, color))
+    }
+
+    /// Piece of a type known in advance, the infallible form of [`Piece::new`].
+    pub const fn from_type(piece_type: PieceType, piece_color: PieceColor) -> Self {
         Self {
-            piece_color: color,
-            piece_type: match name {
-                "king" => PieceType::King(false),
-                "knight" => PieceType::Knight,
-                "rook" => PieceType::Rook(false),
-                "bishop" => PieceType::Bishop,
-                "queen" => PieceType::Queen,
-                _ => PieceType::Pawn(false),
-            },
+            piece_type,
+            piece_color,
         }
     }
 
     //    pub fn get_moves(&self, board: &Board) -> Vec<
 
+    /// Unicode figurine, outlined for White and filled for Black.
     pub fn get_symbol(&self) -> char {
         match self.piece_color {
-            PieceColor::Black => match self.piece_type {
+            PieceColor::White => match self.piece_type {
                 PieceType::King(_) => '\u{2654}',
                 PieceType::Queen => '\u{2655}',
                 PieceType::Rook(_) => '\u{2656}',
@@ -53,7 +120,7 @@ impl Piece {
                 PieceType::Knight => '\u{2658}',
                 PieceType::Pawn(_) => '\u{2659}',
             },
-            PieceColor::White => match self.piece_type {
+            PieceColor::Black => match self.piece_type {
                 PieceType::King(_) => '\u{265A}',
                 PieceType::Queen => '\u{265B}',
                 PieceType::Rook(_) => '\u{265C}',
@@ -63,6 +130,32 @@ impl Piece {
             },
         }
     }
+
+    /// Filled figurine whatever the colour, for drawing where the colour of the
+    /// piece is shown by other means.
+    pub fn get_filled_symbol(&self) -> char {
+        Piece {
+            piece_color: PieceColor::Black,
+            ..*self
+        }
+        .get_symbol()
+    }
+
+    /// Letter as used by FEN, uppercase for White and lowercase for Black.
+    pub fn get_letter(&self) -> char {
+        let letter = match self.piece_type {
+            PieceType::King(_) => 'k',
+            PieceType::Queen => 'q',
+            PieceType::Rook(_) => 'r',
+            PieceType::Bishop => 'b',
+            PieceType::Knight => 'n',
+            PieceType::Pawn(_) => 'p',
+        };
+        match self.piece_color {
+            PieceColor::White => letter.to_ascii_uppercase(),
+            PieceColor::Black => letter,
+        }
+    }
 }
 
 impl Default for Piece {
@@ -74,56 +167,223 @@ impl Default for Piece {
     }
 }
 
+/// Pieces on a 10x12 mailbox: the 8x8 board sits in the middle, surrounded by
+/// empty border squares so that moves off the board need no special casing.
+/// `a1` is square 21, `h1` 28 and `h8` 98.
+///
+/// ```
+/// use yauche_game::{Board, PieceColor};
+///
+/// let board = Board::default();
+/// assert_eq!(board.get_v_moves(&PieceColor::White, &[]).len(), 20);
+/// assert_eq!(Board::square_name(21), "a1");
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Board {
-    pub fields: [Option<Piece>; SIZE],
+    // What stands on each square, changed only through Board::set so that the
+    // piece lists stay in step
+    fields: [Option<Piece>; SIZE],
+    // Squares of the pieces on the playing area as bits, by colour and type
+    pieces: [[u128; 6]; 2],
+    // Zobrist keys of the pieces on all squares xor-ed together, see Board::hash
+    key: u64,
+}
+
+/// Squares in ascending order, taken from a set of them with a bit per square.
+#[derive(Debug, Clone, Copy)]
+pub struct Squares(pub u128);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+impl Board {
+    /// A board without pieces.
+    pub const fn empty() -> Self {
+        Self {
+            fields: [INIT; SIZE],
+            pieces: [[0; 6]; 2],
+            key: 0,
+        }
+    }
+
+    /// A board with `fields` as they are, the piece lists built from them.
+    pub fn from_fields(fields: [Option<Piece>; SIZE]) -> Self {
+        let mut board = Self::empty();
+        for (square, piece) in fields.into_iter().enumerate() {
+            board.set(square, piece);
+        }
+        board
+    }
+
+    /// What stands on each square, indexed by square number.
+    pub fn fields(&self) -> &[Option<Piece>; SIZE] {
+        &self.fields
+    }
+
+    /// Zobrist keys of all pieces on the board, without side to move or en passant.
+    pub(crate) fn piece_keys(&self) -> u64 {
+        self.key
+    }
+
+    /// Puts `piece` on `square`, or empties it, and updates the piece lists and
+    /// the Zobrist key.
+    pub fn set(&mut self, square: usize, piece: Option<Piece>) {
+        if let Some(old) = self.fields[square] {
+            self.pieces[old.piece_color.list_index()][old.piece_type.list_index()] &=
+                !(1 << square);
+            self.key ^= piece_key(&old, square);
+        }
+        if let Some(new) = piece {
+            self.key ^= piece_key(&new, square);
+        }
+        // Pieces off the playing area are left out, the move generator ignores them
+        if let Some(new) = piece.filter(|_| Board::is_playing_square(square)) {
+            self.pieces[new.piece_color.list_index()][new.piece_type.list_index()] |= 1 << square;
+        }
+        self.fields[square] = piece;
+    }
+
+    /// Squares of the pieces of `color` and of the type of `piece_type`, whatever
+    /// its moved flag, as a set with a bit per square.
+    pub fn piece_mask(&self, color: &PieceColor, piece_type: PieceType) -> u128 {
+        self.pieces[color.list_index()][piece_type.list_index()]
+    }
+
+    /// Squares of all pieces of `color`, as a set with a bit per square.
+    pub fn color_mask(&self, color: &PieceColor) -> u128 {
+        self.pieces[color.list_index()]
+            .iter()
+            .fold(0, |mask, pieces| mask | pieces)
+    }
+
+    /// Squares of the pieces of `color` and of the type of `piece_type`.
+    ///
+    /// ```
+    /// use yauche_game::{Board, PieceColor, PieceType};
+    ///
+    /// let board = Board::default();
+    /// let knights: Vec<usize> = board.squares(&PieceColor::White, PieceType::Knight).collect();
+    /// assert_eq!(knights, [22, 27]);
+    /// ```
+    pub fn squares(&self, color: &PieceColor, piece_type: PieceType) -> Squares {
+        Squares(self.piece_mask(color, piece_type))
+    }
 }
 
 impl Default for Board {
     fn default() -> Self {
         let mut default_board: [Option<Piece>; SIZE] = [INIT; SIZE];
-        for i in 0..SIZE {
-            default_board[i] = match i {
-                21 | 28 => Some(Piece::new("rook", PieceColor::White)),
-                22 | 27 => Some(Piece::new("knight", PieceColor::White)),
-                23 | 26 => Some(Piece::new("bishop", PieceColor::White)),
-                24 => Some(Piece::new("queen", PieceColor::White)),
-                25 => Some(Piece::new("king", PieceColor::White)),
-                (31..=38) => Some(Piece::new("pawn", PieceColor::White)),
+        for (i, field) in default_board.iter_mut().enumerate() {
+            *field = match i {
+                21 | 28 => Some(Piece::from_type(PieceType::Rook(false), PieceColor::White)),
+                22 | 27 => Some(Piece::from_type(PieceType::Knight, PieceColor::White)),
+                23 | 26 => Some(Piece::from_type(PieceType::Bishop, PieceColor::White)),
+                24 => Some(Piece::from_type(PieceType::Queen, PieceColor::White)),
+                25 => Some(Piece::from_type(PieceType::King(false), PieceColor::White)),
+                (31..=38) => Some(Piece::from_type(PieceType::Pawn(false), PieceColor::White)),
                 //56 63
                 //55 48
-                91 | 98 => Some(Piece::new("rook", PieceColor::Black)),
-                92 | 97 => Some(Piece::new("knight", PieceColor::Black)),
-                93 | 96 => Some(Piece::new("bishop", PieceColor::Black)),
-                94 => Some(Piece::new("queen", PieceColor::Black)),
-                95 => Some(Piece::new("king", PieceColor::Black)),
-                (81..=88) => Some(Piece::new("pawn", PieceColor::Black)),
+                91 | 98 => Some(Piece::from_type(PieceType::Rook(false), PieceColor::Black)),
+                92 | 97 => Some(Piece::from_type(PieceType::Knight, PieceColor::Black)),
+                93 | 96 => Some(Piece::from_type(PieceType::Bishop, PieceColor::Black)),
+                94 => Some(Piece::from_type(PieceType::Queen, PieceColor::Black)),
+                95 => Some(Piece::from_type(PieceType::King(false), PieceColor::Black)),
+                (81..=88) => Some(Piece::from_type(PieceType::Pawn(false), PieceColor::Black)),
                 _ => None,
             };
         }
-        Self {
-            fields: default_board,
-        }
+        Self::from_fields(default_board)
     }
 }
 
+/// The playing area with White at the bottom, drawn by the default [`Renderer`].
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\n")?;
-        for row in (0..ROWS).rev() {
-            for column in 0..COLUMNS {
-                let piece_idx = (row * 10) + column;
-                write!(
-                    f,
-                    "{} ",
-                    match &self.fields[piece_idx] {
-                        Some(piece) => piece.get_symbol(),
-                        None => ' ',
-                    }
-                )?;
-            }
-            write!(f, "\n")?;
-        }
-        Ok(())
+        write!(f, "{}", Renderer::default().render(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Move;
+    use crate::position::Position;
+
+    fn lists_match(board: &Board) -> bool {
+        board.pieces == Board::from_fields(board.fields).pieces
+    }
+
+    // Plays every line `depth` plies deep, checking the lists at each node
+    fn walk(board: &mut Board, color: PieceColor, log: &mut Vec<Move>, depth: usize) {
+        assert!(lists_match(board), "{:?}", log);
+        if depth == 0 {
+            return;
+        }
+        for mv in board.get_v_moves(&color, log) {
+            let undo = board.make_move(&mv, log);
+            walk(board, color.opposite(), log, depth - 1);
+            board.undo_move(&undo, log);
+        }
+        assert!(lists_match(board), "{:?}", log);
+    }
+
+    #[test]
+    fn piece_lists_follow_the_fields() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkb1r/pp1p1ppp/5n2/2pPp3/8/8/PPP1PPPP/RNBQKBNR w KQkq c6 0 4",
+        ];
+        for fen in fens {
+            let position = Position::from_fen(fen).unwrap();
+            let mut board = position.board;
+            let mut log = position.log.clone();
+            walk(&mut board, position.side, &mut log, 3);
+            assert_eq!(board, position.board);
+        }
+    }
+
+    #[test]
+    fn set_moves_pieces_between_lists() {
+        let mut board = Board::default();
+        let knight = board.fields[22];
+        board.set(22, None);
+        board.set(43, knight);
+        assert!(lists_match(&board));
+        assert_eq!(
+            board
+                .squares(&PieceColor::White, PieceType::Knight)
+                .collect::<Vec<_>>(),
+            [27, 43]
+        );
+        board.set(
+            43,
+            Some(Piece::from_type(PieceType::Queen, PieceColor::Black)),
+        );
+        assert!(lists_match(&board));
+        assert_eq!(
+            board
+                .squares(&PieceColor::White, PieceType::Knight)
+                .collect::<Vec<_>>(),
+            [27]
+        );
+        assert_eq!(
+            board
+                .squares(&PieceColor::Black, PieceType::Queen)
+                .collect::<Vec<_>>(),
+            [43, 94]
+        );
     }
 }
@@ -0,0 +1,327 @@
+use crate::position::Position;
+use crate::search::{Limits, SearchInfo, SearchOptions, SearchResult};
+use crate::smp::{Threads, DEFAULT_HASH_MB};
+use crate::strength::{Random, Skill};
+use crate::time::{TimeManager, DEFAULT_MOVE_OVERHEAD};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receives progress reports while [`Engine::search`] runs.
+pub trait SearchObserver {
+    /// Called once the search has started, from then on it can be stopped.
+    fn on_start(&self) {}
+
+    /// Called after every completed iteration of the main search thread.
+    fn on_iteration(&self, _info: &SearchInfo) {}
+}
+
+/// Observer that ignores everything.
+impl SearchObserver for () {}
+
+/// Controls a running search from another thread. Signals sent before
+/// [`SearchObserver::on_start`] was called are lost.
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    flag: Arc<AtomicBool>,
+    ponderhit: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// Makes the running search return its best move as soon as possible.
+    pub fn stop(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Tells a pondering search that the expected move was played, so it goes on
+    /// as a normal search on the clock it was given.
+    pub fn ponderhit(&self) {
+        self.ponderhit.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The playing engine: a pool of search threads with their shared hash table,
+/// kept alive between searches so later moves benefit from earlier ones.
+pub struct Engine {
+    threads: Threads,
+    /// Time reserved per move for communication delays
+    pub move_overhead: Duration,
+    /// Plays weaker on purpose when limited
+    pub skill: Skill,
+    random: Random,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new(1, DEFAULT_HASH_MB)
+    }
+}
+
+impl Engine {
+    pub fn new(threads: usize, hash_megabytes: usize) -> Self {
+        Self {
+            threads: Threads::new(threads, hash_megabytes, SearchOptions::default()),
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            skill: Skill::default(),
+            random: Random::default(),
+        }
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads.set_threads(threads);
+    }
+
+    pub fn set_hash(&mut self, megabytes: usize) {
+        self.threads.set_hash(megabytes);
+    }
+
+    /// Forgets everything learned from previous searches.
+    pub fn new_game(&mut self) {
+        self.threads.clear();
+    }
+
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle {
+            flag: self.threads.stop_flag(),
+            ponderhit: self.threads.ponderhit_flag(),
+        }
+    }
+
+    /// Searches `position` for the side to move until `limits` are reached or the
+    /// search is stopped through a [`StopHandle`]. The position is left as it was.
+    ///
+    /// With `limits.ponder` the position is the one after the expected reply and
+    /// the clock is kept for after the ponderhit.
+    ///
+    /// With a limited [`Skill`] the best move may be swapped for another of the
+    /// lines searched, whose score and principal variation then become the result's.
+    ///
+    /// ```
+    /// use yauche_game::{Engine, Limits, Position};
+    ///
+    /// // White mates with Rd8
+    /// let mut position = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+    /// let result = Engine::default().search(&mut position, Limits::depth(3), &());
+    /// assert_eq!(result.best_move.unwrap().to_string(), "d1d8");
+    /// ```
+    pub fn search(
+        &mut self,
+        position: &mut Position,
+        mut limits: Limits,
+        observer: &dyn SearchObserver,
+    ) -> SearchResult {
+        // A limited skill searches extra lines to choose from, the observer
+        // only hears of the ones asked for
+        let reported = limits.multi_pv.max(1);
+        self.skill.limit(&mut limits);
+        let time = if limits.infinite {
+            None
+        } else if let Some(move_time) = limits.move_time {
+            Some(TimeManager::fixed(move_time, self.move_overhead))
+        } else if limits.clock.is_timed() {
+            Some(TimeManager::new(
+                &limits.clock,
+                &position.side,
+                self.move_overhead,
+            ))
+        } else {
+            None
+        };
+
+        self.threads.reset_signals();
+        observer.on_start();
+        let mut result = self.threads.search(
+            &position.board,
+            &position.log,
+            &position.side,
+            &limits,
+            time,
+            &mut |info| {
+                if info.multipv <= reported {
+                    observer.on_iteration(info)
+                }
+            },
+        );
+
+        let pick = self.skill.pick(&result.lines, &mut self.random);
+        if pick > 0 {
+            let line = &result.lines[pick];
+            result.best_move = Some(line.best_move);
+            result.score = line.score;
+            result.pv = line.pv.clone();
+        }
+        result.lines.truncate(reported);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Instant;
+
+    /// Tells the test thread once the search can be signalled.
+    struct Started(mpsc::Sender<()>);
+
+    impl SearchObserver for Started {
+        fn on_start(&self) {
+            self.0.send(()).unwrap();
+        }
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let mut engine = Engine::default();
+        let handle = engine.stop_handle();
+        // Stalemate, so that there is nothing to search
+        let mut position = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let limits = Limits {
+            infinite: true,
+            ..Limits::default()
+        };
+        let (sender, started) = mpsc::channel();
+        thread::scope(|scope| {
+            let search = scope.spawn(|| engine.search(&mut position, limits, &Started(sender)));
+            started.recv().unwrap();
+            thread::sleep(Duration::from_millis(100));
+            assert!(!search.is_finished());
+            handle.stop();
+            assert_eq!(search.join().unwrap().best_move, None);
+        });
+    }
+
+    /// Ranks of the lines reported.
+    #[derive(Default)]
+    struct Ranks(RefCell<Vec<usize>>);
+
+    impl SearchObserver for Ranks {
+        fn on_iteration(&self, info: &SearchInfo) {
+            self.0.borrow_mut().push(info.multipv);
+        }
+    }
+
+    #[test]
+    fn limited_skill_reports_only_the_lines_asked_for() {
+        let mut engine = Engine::new(1, DEFAULT_HASH_MB);
+        engine.skill = Skill::level(0);
+        let mut position = Position::default();
+        let ranks = Ranks::default();
+        let result = engine.search(&mut position, Limits::default(), &ranks);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.lines.len(), 1);
+        let ranks = ranks.0.into_inner();
+        assert!(!ranks.is_empty());
+        assert!(ranks.iter().all(|&rank| rank == 1));
+    }
+
+    #[test]
+    fn helpers_stay_within_the_limits() {
+        let mut engine = Engine::new(4, DEFAULT_HASH_MB);
+        let mut position = Position::default();
+        for depth in 1..=5 {
+            let limits = Limits {
+                multi_pv: 3,
+                ..Limits::depth(depth)
+            };
+            let result = engine.search(&mut position, limits, &());
+            assert_eq!(result.depth, depth);
+            assert_eq!(result.lines.len(), 3);
+            assert_eq!(result.best_move, Some(result.lines[0].best_move));
+        }
+    }
+
+    #[test]
+    fn stop_ends_a_search_with_the_best_move_so_far() {
+        let mut engine = Engine::default();
+        let handle = engine.stop_handle();
+        let mut position = Position::default();
+        let (sender, started) = mpsc::channel();
+        thread::scope(|scope| {
+            let search =
+                scope.spawn(|| engine.search(&mut position, Limits::depth(60), &Started(sender)));
+            started.recv().unwrap();
+            thread::sleep(Duration::from_millis(200));
+            let stopped = Instant::now();
+            handle.stop();
+            let result = search.join().unwrap();
+            assert!(stopped.elapsed() < Duration::from_secs(2));
+            assert!(result.depth < 60);
+            assert!(Position::default()
+                .legal_moves()
+                .contains(&result.best_move.unwrap()));
+        });
+    }
+
+    #[test]
+    fn ponder_search_waits_for_ponderhit() {
+        let mut engine = Engine::default();
+        let handle = engine.stop_handle();
+        let mut position = Position::default();
+        let limits = Limits {
+            ponder: true,
+            ..Limits::depth(2)
+        };
+        let (sender, started) = mpsc::channel();
+        thread::scope(|scope| {
+            let search = scope.spawn(|| engine.search(&mut position, limits, &Started(sender)));
+            started.recv().unwrap();
+            thread::sleep(Duration::from_millis(200));
+            // Depth 2 is long done, but the opponent hasn't moved yet
+            assert!(!search.is_finished());
+            handle.ponderhit();
+            let result = search.join().unwrap();
+            assert_eq!(result.depth, 2);
+            assert!(result.best_move.is_some());
+        });
+    }
+
+    #[test]
+    fn stop_ends_pondering() {
+        let mut engine = Engine::default();
+        let handle = engine.stop_handle();
+        let mut position = Position::default();
+        let limits = Limits {
+            ponder: true,
+            ..Limits::depth(2)
+        };
+        let (sender, started) = mpsc::channel();
+        thread::scope(|scope| {
+            let search = scope.spawn(|| engine.search(&mut position, limits, &Started(sender)));
+            started.recv().unwrap();
+            thread::sleep(Duration::from_millis(100));
+            assert!(!search.is_finished());
+            handle.stop();
+            assert!(search.join().unwrap().best_move.is_some());
+        });
+    }
+
+    #[test]
+    fn clock_starts_at_the_ponderhit() {
+        let mut engine = Engine::default();
+        let handle = engine.stop_handle();
+        let mut position = Position::default();
+        let limits = Limits {
+            ponder: true,
+            move_time: Some(Duration::from_millis(300)),
+            ..Limits::default()
+        };
+        let (sender, started) = mpsc::channel();
+        thread::scope(|scope| {
+            let search = scope.spawn(|| engine.search(&mut position, limits, &Started(sender)));
+            started.recv().unwrap();
+            // Longer than the move time, which doesn't run while pondering
+            thread::sleep(Duration::from_millis(500));
+            assert!(!search.is_finished());
+            let ponderhit = Instant::now();
+            handle.ponderhit();
+            let result = search.join().unwrap();
+            let elapsed = ponderhit.elapsed();
+            assert!(elapsed >= Duration::from_millis(150), "{:?}", elapsed);
+            assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+            assert!(result.best_move.is_some());
+        });
+    }
+}
@@ -0,0 +1,102 @@
+use crate::board::PieceColor;
+use std::fmt;
+
+/// Everything that can go wrong when the library is handed bad input.
+///
+/// ```
+/// use yauche_game::{Error, Position};
+///
+/// let position = Position::default();
+/// assert_eq!(
+///     position.parse_move("Ke2"),
+///     Err(Error::IllegalMove("Ke2".to_string()))
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A position that can't be read from FEN or isn't a legal chess position
+    Fen(FenError),
+    /// A move that is malformed or not legal in the position
+    IllegalMove(String),
+    /// Undo with no move left to take back
+    NoMoveToUndo,
+    /// A piece name that isn't one of the six pieces
+    InvalidPiece(String),
+    /// A PGN text that can't be read, other than through a bad FEN or move
+    InvalidPgn(String),
+    /// Bytes that don't hold a position, other than through an illegal one
+    InvalidBytes(String),
+}
+
+/// What is wrong with a FEN string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// No piece placement at all
+    Empty,
+    /// The piece placement doesn't describe 8 ranks of 8 squares
+    Placement(String),
+    /// A letter that isn't a piece
+    Piece(char),
+    /// A pawn on the first or eighth rank
+    PawnOnBackRank,
+    /// A side without exactly one king
+    KingCount(PieceColor),
+    /// The side that just moved left its king in check
+    OpponentInCheck,
+    /// A side to move other than `w` or `b`
+    SideToMove(String),
+    /// A castling right without the king and rook on their home squares
+    Castling(char),
+    /// An en passant square without the pawn that just pushed past it
+    EnPassant(String),
+    /// A halfmove clock or fullmove number that isn't a number
+    MoveCounter(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Fen(error) => write!(f, "invalid FEN: {}", error),
+            Error::IllegalMove(text) => write!(f, "illegal move {}", text),
+            Error::NoMoveToUndo => write!(f, "no move to undo"),
+            Error::InvalidPiece(name) => write!(f, "invalid piece {}", name),
+            Error::InvalidPgn(reason) => write!(f, "invalid PGN: {}", reason),
+            Error::InvalidBytes(reason) => write!(f, "invalid position bytes: {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::Empty => write!(f, "no piece placement"),
+            FenError::Placement(placement) => {
+                write!(
+                    f,
+                    "piece placement {} isn't 8 ranks of 8 squares",
+                    placement
+                )
+            }
+            FenError::Piece(letter) => write!(f, "{} isn't a piece", letter),
+            FenError::PawnOnBackRank => write!(f, "pawn on the first or eighth rank"),
+            FenError::KingCount(color) => write!(f, "{:?} doesn't have exactly one king", color),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            FenError::SideToMove(side) => write!(f, "side to move {} isn't w or b", side),
+            FenError::Castling(right) => {
+                write!(f, "castling right {} without king and rook", right)
+            }
+            FenError::EnPassant(square) => write!(f, "impossible en passant square {}", square),
+            FenError::MoveCounter(counter) => write!(f, "move counter {} isn't a number", counter),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl std::error::Error for FenError {}
+
+impl From<FenError> for Error {
+    fn from(error: FenError) -> Self {
+        Error::Fen(error)
+    }
+}
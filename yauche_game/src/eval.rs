@@ -0,0 +1,145 @@
+use crate::board::{Board, PieceColor, PieceType};
+
+// Piece-square tables from White's point of view, rank 8 first
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+// Below this much non-pawn material on the board the king should come out
+const ENDGAME_MATERIAL: i32 = 2600;
+
+impl Board {
+    /// Static evaluation in centipawns from the point of view of `color`:
+    /// material plus piece-square bonuses.
+    pub fn evaluate(&self, color: &PieceColor) -> i32 {
+        let endgame = self.non_pawn_material(&PieceColor::White)
+            + self.non_pawn_material(&PieceColor::Black)
+            <= ENDGAME_MATERIAL;
+
+        let mut score = 0;
+        for (position, piece) in self.fields().iter().enumerate() {
+            let piece = match piece {
+                Some(piece) => piece,
+                None => continue,
+            };
+            let rank = position / 10 - 2;
+            let file = position % 10 - 1;
+            let idx = match piece.piece_color {
+                PieceColor::White => (7 - rank) * 8 + file,
+                PieceColor::Black => rank * 8 + file,
+            };
+            let bonus = match piece.piece_type {
+                PieceType::Pawn(_) => PAWN_TABLE[idx],
+                PieceType::Knight => KNIGHT_TABLE[idx],
+                PieceType::Bishop => BISHOP_TABLE[idx],
+                PieceType::Rook(_) => ROOK_TABLE[idx],
+                PieceType::Queen => QUEEN_TABLE[idx],
+                PieceType::King(_) if endgame => KING_ENDGAME_TABLE[idx],
+                PieceType::King(_) => KING_TABLE[idx],
+            };
+            let value = match piece.piece_type {
+                PieceType::King(_) => bonus,
+                _ => piece.piece_type.value() + bonus,
+            };
+            if piece.piece_color == *color {
+                score += value;
+            } else {
+                score -= value;
+            }
+        }
+        score
+    }
+
+    /// Value of all knights, bishops, rooks and queens of `color`.
+    pub fn non_pawn_material(&self, color: &PieceColor) -> i32 {
+        [
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook(true),
+            PieceType::Queen,
+        ]
+        .into_iter()
+        .map(|t| self.piece_mask(color, t).count_ones() as i32 * t.value())
+        .sum()
+    }
+}
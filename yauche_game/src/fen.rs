@@ -0,0 +1,405 @@
+use crate::board::{Board, Piece, PieceColor, PieceType};
+use crate::error::{Error, FenError};
+use crate::moves::Move;
+use crate::position::Position;
+
+impl Position {
+    /// Reads a position in Forsyth-Edwards Notation. The move counters are optional.
+    ///
+    /// ```
+    /// use yauche_game::{Error, FenError, PieceColor, Position};
+    ///
+    /// let fen = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2";
+    /// let mut position = Position::from_fen(fen).unwrap();
+    /// let f5 = position.parse_move("f5").unwrap();
+    /// position.make_move(&f5);
+    /// assert!(position.parse_move("exf6").is_ok());
+    ///
+    /// let error = Position::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").unwrap_err();
+    /// assert_eq!(error, Error::Fen(FenError::KingCount(PieceColor::White)));
+    /// ```
+    ///
+    /// Castling rights become unmoved kings and rooks. An en passant square becomes
+    /// the double pawn push that allows it, kept in the log as a setup move.
+    pub fn from_fen(fen: &str) -> Result<Self, Error> {
+        let mut parts = fen.split_whitespace();
+        let placement = parts.next().ok_or(FenError::Empty)?;
+        let side = match parts.next().unwrap_or("w") {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            other => return Err(FenError::SideToMove(other.to_string()).into()),
+        };
+        let castling = parts.next().unwrap_or("-");
+        let en_passant = parts.next().unwrap_or("-");
+        let counter = |text: Option<&str>, default: u32| {
+            text.map_or(Ok(default), |n| {
+                n.parse().map_err(|_| FenError::MoveCounter(n.to_string()))
+            })
+        };
+        let halfmove_clock = counter(parts.next(), 0)?;
+        let fullmove_number = counter(parts.next(), 1)?;
+
+        let mut board = Board::empty();
+        let ranks: Vec<&str> = placement.split('/').collect();
+        let bad_placement = || FenError::Placement(placement.to_string());
+        if ranks.len() != 8 {
+            return Err(bad_placement().into());
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            // The first rank listed is the eighth
+            let rank_start = (9 - row) * 10;
+            let mut file = 1;
+            for symbol in rank.chars() {
+                if let Some(empty) = symbol.to_digit(10) {
+                    if empty == 0 {
+                        return Err(bad_placement().into());
+                    }
+                    file += empty as usize;
+                    continue;
+                }
+                if file > 8 {
+                    return Err(bad_placement().into());
+                }
+                let square = rank_start + file;
+                board.set(square, Some(piece_from_fen(symbol, square)?));
+                file += 1;
+            }
+            if file != 9 {
+                return Err(bad_placement().into());
+            }
+        }
+        let mut rights = [false; 4];
+        if castling != "-" {
+            for right in castling.chars() {
+                let index = "KQkq".find(right).ok_or(FenError::Castling(right))?;
+                rights[index] = true;
+            }
+        }
+
+        let en_passant = match en_passant {
+            "-" => None,
+            square => Some(
+                Board::square_index(square)
+                    .ok_or_else(|| FenError::EnPassant(square.to_string()))?,
+            ),
+        };
+
+        Ok(Self::set_up(
+            board,
+            side,
+            rights,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        )?)
+    }
+
+    /// Checks a position read from FEN or some other format and builds it:
+    /// `castling` holds the rights `KQkq` in that order, which become unmoved
+    /// kings and rooks, and `en_passant` the square a pawn just passed over.
+    pub(crate) fn set_up(
+        mut board: Board,
+        side: PieceColor,
+        castling: [bool; 4],
+        en_passant: Option<usize>,
+        halfmove_clock: u32,
+        fullmove_number: u32,
+    ) -> Result<Self, FenError> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            if board
+                .piece_mask(&color, PieceType::King(false))
+                .count_ones()
+                != 1
+            {
+                return Err(FenError::KingCount(color));
+            }
+        }
+        // The side that just moved can't have left its king in check
+        if board.in_check(&side.opposite()) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        let homes = [
+            (25, 28, PieceColor::White),
+            (25, 21, PieceColor::White),
+            (95, 98, PieceColor::Black),
+            (95, 91, PieceColor::Black),
+        ];
+        for ((right, (king, rook, color)), allowed) in "KQkq".chars().zip(homes).zip(castling) {
+            if allowed
+                && !(unmove(&mut board, king, PieceType::King(false), color)
+                    && unmove(&mut board, rook, PieceType::Rook(false), color))
+            {
+                return Err(FenError::Castling(right));
+            }
+        }
+
+        let mut log = Vec::new();
+        if let Some(square) = en_passant {
+            let bad_square = || FenError::EnPassant(Board::square_name(square));
+            let (source, target) = match side {
+                PieceColor::White if square / 10 == 7 => (square + 10, square - 10),
+                PieceColor::Black if square / 10 == 4 => (square - 10, square + 10),
+                _ => return Err(bad_square()),
+            };
+            let pushed = board.fields()[target].ok_or_else(bad_square)?;
+            if pushed.piece_color == side || !matches!(pushed.piece_type, PieceType::Pawn(_)) {
+                return Err(bad_square());
+            }
+            log.push(Move {
+                source,
+                target,
+                captured: None,
+                promotion: None,
+                ep: None,
+                castle: None,
+            });
+        }
+
+        let mut position = Self::new(board, side);
+        position.setup_moves = log.len();
+        position.log = log;
+        position.halfmove_clock = halfmove_clock;
+        position.fullmove_number = fullmove_number.max(1);
+        Ok(position)
+    }
+
+    /// Writes the position in Forsyth-Edwards Notation.
+    ///
+    /// ```
+    /// use yauche_game::Position;
+    ///
+    /// let mut position = Position::default();
+    /// let e4 = position.parse_move("e4").unwrap();
+    /// position.make_move(&e4);
+    /// assert_eq!(
+    ///     position.to_fen(),
+    ///     "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    /// );
+    /// ```
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (2..10).rev() {
+            let mut empty = 0;
+            for file in 1..9 {
+                match self.board.fields()[rank * 10 + file] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece.get_letter());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 2 {
+                placement.push('/');
+            }
+        }
+
+        let side = match self.side {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+
+        let castling: String = ['K', 'Q', 'k', 'q']
+            .into_iter()
+            .zip(self.board.castling_rights())
+            .filter_map(|(right, allowed)| allowed.then_some(right))
+            .collect();
+        let castling = if castling.is_empty() {
+            "-".to_string()
+        } else {
+            castling
+        };
+
+        // Set after every double pawn push, whether a capture is possible or not
+        let en_passant = self
+            .board
+            .en_passant(&self.log)
+            .map_or("-".to_string(), Board::square_name);
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement, side, castling, en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+}
+
+fn piece_from_fen(symbol: char, square: usize) -> Result<Piece, FenError> {
+    let color = if symbol.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    let piece_type = match symbol.to_ascii_lowercase() {
+        'p' => PieceType::Pawn(true),
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook(true),
+        'q' => PieceType::Queen,
+        'k' => PieceType::King(true),
+        _ => return Err(FenError::Piece(symbol)),
+    };
+    set_up_piece(piece_type, color, square)
+}
+
+/// The piece as it stands on `square` before the castling rights are known.
+/// Only what the castling rights allow is unmoved, pawns can still make a
+/// double step from their home rank.
+pub(crate) fn set_up_piece(
+    piece_type: PieceType,
+    color: PieceColor,
+    square: usize,
+) -> Result<Piece, FenError> {
+    let piece_type = match piece_type {
+        PieceType::Pawn(_) => match (color, square / 10) {
+            (_, 2) | (_, 9) => return Err(FenError::PawnOnBackRank),
+            (PieceColor::White, 3) | (PieceColor::Black, 8) => PieceType::Pawn(false),
+            _ => PieceType::Pawn(true),
+        },
+        PieceType::Rook(_) => PieceType::Rook(true),
+        PieceType::King(_) => PieceType::King(true),
+        other => other,
+    };
+    Ok(Piece::from_type(piece_type, color))
+}
+
+/// Marks the piece of `color` on `square` as not moved yet, if it is the expected one.
+fn unmove(board: &mut Board, square: usize, unmoved: PieceType, color: PieceColor) -> bool {
+    match board.fields()[square] {
+        Some(piece)
+            if piece.piece_color == color
+                && std::mem::discriminant(&piece.piece_type)
+                    == std::mem::discriminant(&unmoved) =>
+        {
+            board.set(square, Some(Piece::from_type(unmoved, color)));
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(fen: &str) -> FenError {
+        match Position::from_fen(fen) {
+            Err(Error::Fen(error)) => error,
+            other => panic!("{}: {:?}", fen, other.map(|p| p.to_fen())),
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(error(""), FenError::Empty);
+        assert_eq!(error("  "), FenError::Empty);
+    }
+
+    #[test]
+    fn placement() {
+        for placement in [
+            "4k3/8/8/8/8/8/4K3",
+            "4k3/8/8/8/8/8/8/4K4",
+            "4k3/8/8/8/8/8/8/4K2",
+            "4k3/8/8/8/8/8/8/9",
+            "4k3/8/8/8/8/8/8/03K4",
+        ] {
+            assert_eq!(
+                error(&format!("{} w - - 0 1", placement)),
+                FenError::Placement(placement.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn piece() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3XK3 w - - 0 1"),
+            FenError::Piece('X')
+        );
+    }
+
+    #[test]
+    fn pawn_on_back_rank() {
+        assert_eq!(
+            error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::PawnOnBackRank
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/p3K3 w - - 0 1"),
+            FenError::PawnOnBackRank
+        );
+    }
+
+    #[test]
+    fn king_count() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/8 w - - 0 1"),
+            FenError::KingCount(PieceColor::White)
+        );
+        assert_eq!(
+            error("3kk3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::KingCount(PieceColor::Black)
+        );
+    }
+
+    #[test]
+    fn opponent_in_check() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+            FenError::OpponentInCheck
+        );
+    }
+
+    #[test]
+    fn side_to_move() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            FenError::SideToMove("x".to_string())
+        );
+    }
+
+    #[test]
+    fn castling() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            FenError::Castling('K')
+        );
+        assert_eq!(
+            error("r3k3/8/8/8/8/8/8/4K3 w k - 0 1"),
+            FenError::Castling('k')
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2R w X - 0 1"),
+            FenError::Castling('X')
+        );
+    }
+
+    #[test]
+    fn en_passant() {
+        for square in ["e6", "e3", "z9"] {
+            assert_eq!(
+                error(&format!("4k3/8/8/8/8/8/8/4K3 w - {} 0 1", square)),
+                FenError::EnPassant(square.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn move_counter() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+            FenError::MoveCounter("x".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 -1"),
+            FenError::MoveCounter("-1".to_string())
+        );
+    }
+}
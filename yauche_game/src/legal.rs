@@ -0,0 +1,249 @@
+use crate::board::{Board, PieceColor, PieceType};
+use crate::movelist::MoveList;
+use crate::moves::Move;
+
+// Rays out of the king, the first four straight and the rest diagonal
+const DIRECTIONS: [isize; 8] = [10, -10, 1, -1, 11, -11, 9, -9];
+
+fn bit(square: usize) -> u128 {
+    1 << square
+}
+
+/// What keeps the pieces of one side from moving freely: the pieces giving
+/// check and the pieces pinned to their king. Squares are bits of a `u128`,
+/// the mailbox has fewer than 128 of them.
+struct KingSafety {
+    king: usize,
+    checkers: u32,
+    /// Squares a move other than the king's has to land on: the checking piece
+    /// and, for a slider, the squares between it and the king. Every square
+    /// when not in check.
+    check_mask: u128,
+    pinned: u128,
+    /// For each direction out of the king, the squares up to and including
+    /// the piece pinning along it
+    pin_rays: [u128; 8],
+}
+
+impl KingSafety {
+    /// Finds checks and pins against the king of `color`. `None` unless that
+    /// side has exactly one king on the board.
+    fn new(board: &Board, color: &PieceColor) -> Option<Self> {
+        let kings = board.piece_mask(color, PieceType::King(false));
+        if kings.count_ones() != 1 {
+            return None;
+        }
+        let king = kings.trailing_zeros() as usize;
+
+        let mut safety = KingSafety {
+            king,
+            checkers: 0,
+            check_mask: 0,
+            pinned: 0,
+            pin_rays: [0; 8],
+        };
+        for (index, step) in DIRECTIONS.into_iter().enumerate() {
+            let diagonal = index >= 4;
+            let mut ray = 0;
+            let mut own = None;
+            let mut square = king as isize + step;
+            while Board::is_playing_square(square as usize) {
+                ray |= bit(square as usize);
+                if let Some(piece) = board.fields()[square as usize] {
+                    if piece.piece_color == *color {
+                        if own.is_some() {
+                            break;
+                        }
+                        own = Some(square as usize);
+                    } else {
+                        let slides = match piece.piece_type {
+                            PieceType::Queen => true,
+                            PieceType::Bishop => diagonal,
+                            PieceType::Rook(_) => !diagonal,
+                            _ => false,
+                        };
+                        match own {
+                            None if slides => {
+                                safety.checkers += 1;
+                                safety.check_mask |= ray;
+                            }
+                            Some(pinned) if slides => {
+                                safety.pinned |= bit(pinned);
+                                safety.pin_rays[index] = ray;
+                            }
+                            _ => {}
+                        }
+                        break;
+                    }
+                }
+                square += step;
+            }
+        }
+
+        // Pawns and knights can only check from where they stand
+        let pawn_steps = match color {
+            PieceColor::White => [9, 11],
+            PieceColor::Black => [-9, -11],
+        };
+        let leapers = [19, 21, 12, -8, -19, -21, -12, 8]
+            .map(|offset| (offset, PieceType::Knight))
+            .into_iter()
+            .chain(pawn_steps.map(|offset| (offset, PieceType::Pawn(true))));
+        for (offset, piece_type) in leapers {
+            let square = (king as isize + offset) as usize;
+            if !Board::is_playing_square(square) {
+                continue;
+            }
+            if let Some(piece) = board.fields()[square] {
+                if piece.piece_color != *color
+                    && std::mem::discriminant(&piece.piece_type)
+                        == std::mem::discriminant(&piece_type)
+                {
+                    safety.checkers += 1;
+                    safety.check_mask |= bit(square);
+                }
+            }
+        }
+
+        if safety.checkers == 0 {
+            safety.check_mask = !0;
+        }
+        Some(safety)
+    }
+
+    /// Whether the pseudo-legal `mv` leaves the king out of check.
+    fn allows(&self, board: &Board, mv: &Move, color: &PieceColor) -> bool {
+        if mv.source == self.king {
+            // Lifted off the board, so that it can't hide behind itself from a
+            // slider checking along the line it steps on
+            let mut without_king = *board;
+            without_king.set(self.king, None);
+            return !without_king.is_attacked(mv.target, &color.opposite());
+        }
+        // Two pawns leave the rank of the king at once, which no pin ray
+        // describes, so en passant is played out
+        if mv.ep.is_some() {
+            return board.is_legal(mv, color);
+        }
+        if self.checkers > 1 || self.check_mask & bit(mv.target) == 0 {
+            return false;
+        }
+        self.pinned & bit(mv.source) == 0
+            || self
+                .pin_rays
+                .iter()
+                .any(|ray| ray & bit(mv.source) != 0 && ray & bit(mv.target) != 0)
+    }
+}
+
+impl Board {
+    /// Legal moves of `color`, the last move in `log` decides about en passant.
+    ///
+    /// Pseudo-legal moves are filtered with the checks and pins against the king
+    /// instead of being played out one by one. Debug builds compare the result
+    /// with playing them out.
+    pub fn get_v_moves(&self, color: &PieceColor, log: &[Move]) -> MoveList {
+        let mut moves = self.get_pv_moves(color, log);
+        match KingSafety::new(self, color) {
+            Some(safety) => moves.retain(|m| safety.allows(self, m, color)),
+            None => moves.retain(|m| self.is_legal(m, color)),
+        }
+        debug_assert!(
+            {
+                let mut played_out = self.get_pv_moves(color, log);
+                played_out.retain(|m| self.is_legal(m, color));
+                played_out[..] == moves[..]
+            },
+            "legal moves differ from playing out the pseudo-legal ones"
+        );
+        moves
+    }
+
+    /// Whether the pseudo-legal `mv` keeps the king of `color` out of check,
+    /// found by playing it on a copy of the board.
+    pub fn is_legal(&self, mv: &Move, color: &PieceColor) -> bool {
+        let mut board = *self;
+        board.move_pieces(mv);
+        board.validate(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::position::Position;
+
+    fn sans(fen: &str) -> Vec<String> {
+        let position = Position::from_fen(fen).unwrap();
+        let mut moves: Vec<String> = position
+            .legal_moves()
+            .iter()
+            .map(|mv| position.to_san(mv))
+            .collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn perft_positions() {
+        // Kiwipete and positions 3 to 6 of the Chess Programming Wiki
+        let positions = [
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                [48, 2039, 97862],
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", [14, 191, 2812]),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                [6, 264, 9467],
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                [44, 1486, 62379],
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                [46, 2079, 89890],
+            ),
+        ];
+        for (fen, counts) in positions {
+            let position = Position::from_fen(fen).unwrap();
+            for (depth, count) in counts.into_iter().enumerate() {
+                assert_eq!(
+                    position
+                        .board
+                        .perft(depth + 1, &position.log, &position.side),
+                    count,
+                    "{} depth {}",
+                    fen,
+                    depth + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn en_passant_uncovering_a_check_along_the_rank() {
+        let moves = sans("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1");
+        assert!(!moves.contains(&"bxc6".to_string()));
+        assert!(moves.contains(&"b6".to_string()));
+        // With the rook gone the capture is fine
+        assert!(sans("8/8/8/KPp5/8/8/8/4k3 w - c6 0 1").contains(&"bxc6".to_string()));
+    }
+
+    #[test]
+    fn double_check_leaves_only_king_moves() {
+        // Rook and knight both check, taking either one doesn't help
+        assert_eq!(sans("4k3/R7/8/8/8/5n2/6B1/r3K3 w - - 0 1"), ["Ke2", "Kf2"]);
+    }
+
+    #[test]
+    fn pinned_sliders_move_along_the_pin() {
+        let moves = sans("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+        let rook: Vec<&String> = moves.iter().filter(|m| m.starts_with('R')).collect();
+        assert_eq!(rook, ["Re3", "Re4", "Re5", "Re6", "Rxe7+"]);
+
+        let moves = sans("4k3/8/8/8/8/2b5/3B4/4K3 w - - 0 1");
+        let bishop: Vec<&String> = moves.iter().filter(|m| m.starts_with('B')).collect();
+        assert_eq!(bishop, ["Bxc3"]);
+    }
+}
@@ -0,0 +1,65 @@
+//! YAUCHE, a chess engine: a mailbox board with legal move generation, FEN and
+//! SAN notation, and an alpha-beta search that speaks UCI and xboard.
+//!
+//! The types most programs need are re-exported here. A game is kept in a
+//! [`Position`], which knows the side to move and the moves played so far:
+//!
+//! ```
+//! use yauche_game::{Engine, Limits, Position};
+//!
+//! let mut position = Position::default();
+//! let e4 = position.parse_move("e4").unwrap();
+//! position.make_move(&e4);
+//! assert_eq!(position.legal_moves().len(), 20);
+//!
+//! let mut engine = Engine::default();
+//! let result = engine.search(&mut position, Limits::depth(3), &());
+//! assert!(result.best_move.is_some());
+//! ```
+
+pub mod attacks;
+mod binary;
+pub mod board;
+pub mod engine;
+mod error;
+pub mod eval;
+mod fen;
+mod legal;
+pub mod motifs;
+pub mod movelist;
+pub mod moves;
+mod notation;
+mod packed;
+pub mod pgn;
+mod play;
+pub mod position;
+pub mod render;
+mod search;
+mod see;
+mod smp;
+pub mod solver;
+pub mod strength;
+pub mod svg;
+pub mod time;
+mod tt;
+mod uci;
+mod xboard;
+mod zobrist;
+
+pub use attacks::AttackMap;
+pub use binary::BINARY_VERSION;
+pub use board::{Board, Piece, PieceColor, PieceType};
+pub use engine::{Engine, SearchObserver, StopHandle};
+pub use error::{Error, FenError};
+pub use motifs::Motif;
+pub use movelist::MoveList;
+pub use moves::{Move, UndoInfo};
+pub use packed::PackedMove;
+pub use play::{Play, PlaySettings};
+pub use position::Position;
+pub use search::{mate_in, Limits, PvLine, SearchInfo, SearchResult};
+pub use smp::DEFAULT_HASH_MB;
+pub use strength::Skill;
+pub use time::TimeControl;
+pub use uci::Uci;
+pub use xboard::XBoard;
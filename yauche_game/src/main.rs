@@ -1,54 +1,413 @@
-use crate::board::Board;
+use std::env;
+use std::fs;
+use std::process;
+use std::time::{Duration, Instant};
+use yauche_game::pgn::Pgn;
+use yauche_game::render::{Glyphs, Renderer};
+use yauche_game::solver::{Solution, Solver, Stipulation};
+use yauche_game::strength::Random;
+use yauche_game::svg::SvgRenderer;
+use yauche_game::{
+    mate_in, Board, Engine, Limits, PieceColor, Play, PlaySettings, Position, SearchInfo,
+    SearchObserver, Uci, XBoard, DEFAULT_HASH_MB,
+};
 
-mod board;
-mod moves;
+const USAGE: &str = "\
+Usage: yauche_game <command> [options]
+
+Commands:
+  uci                          speak the Universal Chess Interface on stdin/stdout
+  xboard                       speak the xboard/WinBoard protocol on stdin/stdout
+  perft <depth> [--divide]     count the leaf nodes of the move tree
+  analyze [--depth N | --movetime MS] [--multipv N] [--threads N]
+                               search the position and print its lines
+  play [--color white|black|random] [--time MINUTES+INCREMENT] [--movetime MS]
+       [--pgn FILE]            play a game against the engine in the terminal
+  fen [--flip]                 print the board and FEN of the position
+  solve --mate N | --helpmate N | --selfmate N
+                               solve a chess problem, listing every key move with
+                               its full tree, cooks and duals
+  svg [--pgn FILE [--ply N]] [--output FILE] [--flip] [--no-coordinates]
+      [--arrow e2e4]... [--highlight e4]... [--size PIXELS]
+                               draw the position, or the game after N plies, as SVG
+
+Every command but uci and xboard takes the position as
+  [--fen \"<FEN>\"] [moves...]   start position, or the FEN, then moves in SAN or UCI
+Boards are drawn with figurines on coloured squares, unless
+  [--ascii] [--no-color]       ask for letters or plain text";
 
 fn main() {
-    let mut board = board::Board::default();
-    let a = moves::Move {
-        captured: None,
-        promotion: None,
-        source: 85,
-        target: 65,
-        ep: None,
-        castle: None,
-    };
-    let test = moves::Move {
-        captured: None,
-        promotion: None,
-        source: 34,
-        target: 64,
-        ep: None,
-        castle: None,
-    };
-    let tes2 = moves::Move {
-        captured: None,
-        promotion: None,
-        source: 33,
-        target: 63,
-        ep: None,
-        castle: None,
-    };
-    let mut move_log: Vec<moves::Move> = Vec::new();
-
-    //board.make_move(&test, &mut move_log);
-    board.make_move(&a, &mut move_log);
-    //board.make_move(&tes2, &mut move_log);
-    println!("Current board state is: {}", board);
-    println!(
-        "Found {} valid moves for white!",
-        board
-            .get_v_moves(&board::PieceColor::White, &move_log)
-            .len()
-    );
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("uci") => {
+            Uci::default().run();
+            Ok(())
+        }
+        Some("xboard") => {
+            XBoard::default().run();
+            Ok(())
+        }
+        Some("perft") => perft(&args[1..]),
+        Some("analyze") => analyze(&args[1..]),
+        Some("play") => play(&args[1..]),
+        Some("fen") => fen(&args[1..]),
+        Some("solve") => solve(&args[1..]),
+        Some("svg") => svg(&args[1..]),
+        Some("help") | Some("--help") | None => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown command {}", other)),
+    };
+    if let Err(message) = result {
+        eprintln!("error: {}\nRun `yauche_game help` for the usage.", message);
+        process::exit(1);
+    }
+}
+
+/// Command line options after the command name.
+#[derive(Default)]
+struct Options {
+    position: Position,
+    /// Arguments that are neither options nor moves, in order
+    values: Vec<String>,
+    depth: Option<i32>,
+    move_time: Option<Duration>,
+    multi_pv: usize,
+    threads: usize,
+    divide: bool,
+    color: Option<PieceColor>,
+    /// Starting time and increment
+    clock: Option<(Duration, Duration)>,
+    pgn: Option<String>,
+    renderer: Renderer,
+    arrows: Vec<(usize, usize)>,
+    ply: Option<usize>,
+    output: Option<String>,
+    square_size: Option<u32>,
+    stipulation: Option<Stipulation>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            multi_pv: 1,
+            threads: 1,
+            color: Some(PieceColor::White),
+            renderer: Renderer {
+                colors: true,
+                ..Renderer::default()
+            },
+            ..Options::default()
+        };
+        let mut args = args.iter();
+        let mut moves = Vec::new();
+        while let Some(arg) = args.next() {
+            let mut value =
+                |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+            match arg.as_str() {
+                "--fen" => {
+                    let fen = value("--fen")?;
+                    options.position = Position::from_fen(fen).map_err(|e| e.to_string())?;
+                }
+                "--depth" => options.depth = Some(number(value("--depth")?)?),
+                "--movetime" => {
+                    options.move_time = Some(Duration::from_millis(number(value("--movetime")?)?))
+                }
+                "--multipv" => options.multi_pv = number(value("--multipv")?)?,
+                "--threads" => options.threads = number(value("--threads")?)?,
+                "--divide" => options.divide = true,
+                "--color" => {
+                    options.color = match value("--color")?.as_str() {
+                        "white" => Some(PieceColor::White),
+                        "black" => Some(PieceColor::Black),
+                        "random" => None,
+                        other => return Err(format!("unknown colour {}", other)),
+                    }
+                }
+                "--time" => options.clock = Some(clock(value("--time")?)?),
+                "--pgn" => options.pgn = Some(value("--pgn")?.clone()),
+                "--ascii" => options.renderer.glyphs = Glyphs::Ascii,
+                "--no-color" => options.renderer.colors = false,
+                "--flip" => options.renderer.orientation = PieceColor::Black,
+                "--no-coordinates" => options.renderer.coordinates = false,
+                "--highlight" => {
+                    let name = value("--highlight")?;
+                    let square = Board::square_index(name)
+                        .ok_or_else(|| format!("invalid square {}", name))?;
+                    options.renderer.highlights.push(square);
+                }
+                "--arrow" => {
+                    let text = value("--arrow")?;
+                    let squares = text.get(..2).zip(text.get(2..)).and_then(|(from, to)| {
+                        Board::square_index(from).zip(Board::square_index(to))
+                    });
+                    options
+                        .arrows
+                        .push(squares.ok_or_else(|| format!("invalid arrow {}", text))?);
+                }
+                "--ply" => options.ply = Some(number(value("--ply")?)?),
+                "--output" => options.output = Some(value("--output")?.clone()),
+                "--size" => options.square_size = Some(number(value("--size")?)?),
+                "--mate" => {
+                    options.stipulation = Some(Stipulation::Mate(number(value("--mate")?)?))
+                }
+                "--helpmate" => {
+                    options.stipulation = Some(Stipulation::Helpmate(number(value("--helpmate")?)?))
+                }
+                "--selfmate" => {
+                    options.stipulation = Some(Stipulation::Selfmate(number(value("--selfmate")?)?))
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ if arg.parse::<u64>().is_ok() => options.values.push(arg.clone()),
+                _ => moves.push(arg),
+            }
+        }
+        // Moves are only played once the FEN is known, wherever it was given
+        for text in moves {
+            let mv = options
+                .position
+                .parse_move(text)
+                .map_err(|e| e.to_string())?;
+            options.position.make_move(&mv);
+        }
+        Ok(options)
+    }
+}
+
+fn number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{} is not a number", text))
+}
+
+/// Time control written as `minutes+increment`, e.g. `5+3` or `0.5+0`.
+fn clock(text: &str) -> Result<(Duration, Duration), String> {
+    let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+    let seconds = |t: &str| {
+        t.parse::<f64>()
+            .ok()
+            .filter(|t| *t >= 0.0)
+            .ok_or_else(|| format!("invalid time control {}", text))
+    };
+    Ok((
+        Duration::from_secs_f64(seconds(base)? * 60.0),
+        Duration::from_secs_f64(seconds(increment)?),
+    ))
+}
+
+fn perft(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let depth: usize = number(options.values.first().ok_or("perft needs a depth")?)?;
+    let position = &options.position;
+    let start = Instant::now();
+    let nodes = if options.divide && depth > 0 {
+        let mut total = 0;
+        for mv in position.legal_moves() {
+            let mut child = position.clone();
+            child.make_move(&mv);
+            let nodes = child.board.perft(depth - 1, &child.log, &child.side);
+            println!("{}: {}", mv, nodes);
+            total += nodes;
+        }
+        total
+    } else {
+        position.board.perft(depth, &position.log, &position.side)
+    };
+    let elapsed = start.elapsed();
+    println!("Nodes: {}", nodes);
     println!(
-        "Found {:?} valid moves for black!",
-        board
-            .get_v_moves(&board::PieceColor::Black, &move_log)
-            .len()
+        "Time: {} ms ({} nodes/s)",
+        elapsed.as_millis(),
+        nodes * 1000 / elapsed.as_millis().max(1)
     );
+    Ok(())
+}
 
-    let perft_board = board::Board::default();
-    let mut move_log: Vec<moves::Move> = Vec::new();
-    println!("Perft result: {}", perft_board.perft(6, &mut move_log, &board::PieceColor::White));
+fn analyze(args: &[String]) -> Result<(), String> {
+    let mut options = Options::parse(args)?;
+    let limits = Limits {
+        depth: options.depth.or(options.move_time.is_none().then_some(8)),
+        move_time: options.move_time,
+        multi_pv: options.multi_pv,
+        ..Limits::default()
+    };
+    let mut engine = Engine::new(options.threads, DEFAULT_HASH_MB);
+    let observer = AnalysisObserver {
+        position: options.position.clone(),
+    };
+    let result = engine.search(&mut options.position, limits, &observer);
+    match result.best_move {
+        Some(mv) => println!("Best move: {}", options.position.to_san(&mv)),
+        None => println!("No legal moves"),
+    }
+    Ok(())
+}
+
+/// Prints every finished line in SAN.
+struct AnalysisObserver {
+    position: Position,
+}
+
+impl SearchObserver for AnalysisObserver {
+    fn on_iteration(&self, info: &SearchInfo) {
+        let mut position = self.position.clone();
+        let mut line = Vec::new();
+        for mv in info.pv {
+            line.push(position.to_san(mv));
+            position.make_move(mv);
+        }
+        let score = match mate_in(info.score) {
+            Some(moves) => format!("#{}", moves),
+            None => format!("{:+.2}", info.score as f64 / 100.0),
+        };
+        println!(
+            "depth {:>2} line {} {:>7} nodes {:>9} time {:>6} ms  {}",
+            info.depth,
+            info.multipv,
+            score,
+            info.nodes,
+            info.time.as_millis(),
+            line.join(" ")
+        );
+    }
+}
+
+fn play(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let human = options.color.unwrap_or_else(|| {
+        if Random::default().next_u64() % 2 == 0 {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        }
+    });
+    let mut settings = PlaySettings {
+        start: options.position,
+        human,
+        clock: options.clock,
+        pgn: options.pgn,
+        renderer: options.renderer,
+        ..PlaySettings::default()
+    };
+    if let Some(move_time) = options.move_time {
+        settings.move_time = move_time;
+    }
+    Play::new(settings).run();
+    Ok(())
+}
+
+fn fen(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    print!("{}", options.renderer.render(&options.position.board));
+    println!("{}", options.position.to_fen());
+    Ok(())
+}
+
+fn solve(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let stipulation = options
+        .stipulation
+        .ok_or("solve needs --mate N, --helpmate N or --selfmate N")?;
+    let start = Instant::now();
+    let solutions = Solver::new(stipulation).solve(&options.position);
+    println!("{} {}", stipulation, options.position.to_fen());
+    if solutions.is_empty() {
+        println!("No solution");
+        return Ok(());
+    }
+    print_solutions(&options.position, &solutions, 0);
+
+    let duals: usize = solutions.iter().map(Solution::duals).sum();
+    match stipulation {
+        Stipulation::Helpmate(_) => {
+            let lines: usize = solutions.iter().map(|s| s.lines().len()).sum();
+            println!("{} solution{}", lines, if lines == 1 { "" } else { "s" });
+        }
+        _ if solutions.len() > 1 => println!("Cooked: {} key moves", solutions.len()),
+        _ => println!("Sound key"),
+    }
+    println!("Duals: {}", duals);
+    println!("Time: {} ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+/// Prints a solution tree in SAN, the key moves marked with `!` and replies
+/// with more than one answer as duals.
+fn print_solutions(position: &Position, solutions: &[Solution], indent: usize) {
+    for solution in solutions {
+        let key = if indent == 0 { "!" } else { "" };
+        println!(
+            "{:indent$}{}{}{}",
+            "",
+            move_number(position),
+            position.to_san(&solution.mv),
+            key,
+        );
+        let mut after = position.clone();
+        after.make_move(&solution.mv);
+        for reply in &solution.replies {
+            let dual = if reply.answers.len() > 1 {
+                " (dual)"
+            } else {
+                ""
+            };
+            println!(
+                "{:indent$}{}{}{}",
+                "",
+                move_number(&after),
+                after.to_san(&reply.mv),
+                dual,
+                indent = indent + 2
+            );
+            let mut next = after.clone();
+            next.make_move(&reply.mv);
+            print_solutions(&next, &reply.answers, indent + 4);
+        }
+    }
+}
+
+fn move_number(position: &Position) -> String {
+    match position.side {
+        PieceColor::White => format!("{}. ", position.fullmove_number),
+        PieceColor::Black => format!("{}... ", position.fullmove_number),
+    }
+}
+
+fn svg(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let position = match &options.pgn {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let pgn = Pgn::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+            let ply = options.ply.unwrap_or(pgn.moves.len());
+            pgn.position_at(ply)
+                .ok_or_else(|| format!("the game has only {} plies", pgn.moves.len()))?
+        }
+        None => options.position,
+    };
+
+    let mut highlights = options.renderer.highlights;
+    // Without any marks asked for, show the last move
+    if highlights.is_empty() && options.arrows.is_empty() {
+        if let Some(last) = position.log[position.setup_moves..].last() {
+            highlights = vec![last.source, last.target];
+        }
+    }
+    let renderer = SvgRenderer {
+        orientation: options.renderer.orientation,
+        coordinates: options.renderer.coordinates,
+        highlights,
+        arrows: options.arrows,
+        square_size: options
+            .square_size
+            .unwrap_or(SvgRenderer::default().square_size),
+    };
+    let svg = renderer.render(&position.board);
+    match options.output {
+        Some(path) => fs::write(&path, svg).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", svg);
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,291 @@
+use crate::board::{Board, Piece, PieceColor, PieceType, Squares};
+
+const STRAIGHT: [isize; 4] = [10, -10, 1, -1];
+const DIAGONAL: [isize; 4] = [11, -11, 9, -9];
+const QUEEN: [isize; 8] = [10, -10, 1, -1, 11, -11, 9, -9];
+const KNIGHT: [isize; 8] = [19, 21, 12, -8, -19, -21, -12, 8];
+
+/// A tactical pattern found by [`Board::find_motifs`], with the squares of the
+/// pieces involved. The side it favours is the colour of the first piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Motif {
+    /// A knight or pawn attacking two or more pieces that are the king, worth
+    /// more than itself or not defended
+    Fork {
+        attacker: usize,
+        targets: Vec<usize>,
+    },
+    /// A slider attacking a piece that can't move off the line without
+    /// exposing the piece behind it: the king for an absolute pin, something
+    /// more valuable for a relative one
+    Pin {
+        pinner: usize,
+        pinned: usize,
+        behind: usize,
+        absolute: bool,
+    },
+    /// A slider attacking a piece that, stepping aside, leaves a cheaper piece
+    /// behind it to be taken
+    Skewer {
+        attacker: usize,
+        front: usize,
+        behind: usize,
+    },
+    /// A piece standing between a slider of its own side and an enemy piece
+    /// that is the king or worth more than the slider, so that moving it away
+    /// uncovers an attack
+    DiscoveredAttack {
+        slider: usize,
+        blocker: usize,
+        target: usize,
+    },
+    /// A piece that is the only defender of two or more attacked pieces, and
+    /// can't keep guarding all of them
+    Overloaded {
+        defender: usize,
+        defended: Vec<usize>,
+    },
+}
+
+impl Board {
+    /// Forks, pins, skewers, discovered attacks and overloaded defenders of
+    /// both sides, in that order and by square within each kind. Pieces attack
+    /// as in [`Board::attackers`], whoever is to move.
+    ///
+    /// ```
+    /// use yauche_game::{Motif, Position};
+    ///
+    /// // The knight on c7 forks the rook on a8 and the king on e8
+    /// let position = Position::from_fen("r3k3/2N5/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+    /// assert_eq!(
+    ///     position.board.find_motifs(),
+    ///     [Motif::Fork { attacker: 83, targets: vec![91, 95] }]
+    /// );
+    ///
+    /// // The rook on e1 pins the knight on e7 to the king on e8
+    /// let position = Position::from_fen("4k3/4n3/8/8/8/8/8/4RK2 w - - 0 1").unwrap();
+    /// assert_eq!(
+    ///     position.board.find_motifs(),
+    ///     [Motif::Pin { pinner: 25, pinned: 85, behind: 95, absolute: true }]
+    /// );
+    /// ```
+    pub fn find_motifs(&self) -> Vec<Motif> {
+        let mut forks = Vec::new();
+        let mut pins = Vec::new();
+        let mut skewers = Vec::new();
+        let mut discovered = Vec::new();
+        let mut overloaded = Vec::new();
+        for color in [PieceColor::White, PieceColor::Black] {
+            self.find_forks(&color, &mut forks);
+            self.find_lines(&color, &mut pins, &mut skewers, &mut discovered);
+            self.find_overloaded(&color, &mut overloaded);
+        }
+        let first = |motif: &Motif| match motif {
+            Motif::Fork { attacker, .. } => *attacker,
+            Motif::Pin { pinner, .. } => *pinner,
+            Motif::Skewer { attacker, .. } => *attacker,
+            Motif::DiscoveredAttack { slider, .. } => *slider,
+            Motif::Overloaded { defender, .. } => *defender,
+        };
+        let mut motifs = Vec::new();
+        for mut kind in [forks, pins, skewers, discovered, overloaded] {
+            kind.sort_by_key(first);
+            motifs.append(&mut kind);
+        }
+        motifs
+    }
+
+    fn find_forks(&self, color: &PieceColor, motifs: &mut Vec<Motif>) {
+        let pawn_steps: &[isize] = match color {
+            PieceColor::White => &[9, 11],
+            PieceColor::Black => &[-9, -11],
+        };
+        let forkers = Squares(self.piece_mask(color, PieceType::Knight))
+            .map(|square| (square, &KNIGHT[..]))
+            .chain(
+                Squares(self.piece_mask(color, PieceType::Pawn(false)))
+                    .map(|square| (square, pawn_steps)),
+            );
+        for (attacker, steps) in forkers {
+            let value = self.value_at(attacker);
+            let mut targets: Vec<usize> = steps
+                .iter()
+                .map(|step| (attacker as isize + step) as usize)
+                .filter(|&square| {
+                    self.enemy_at(square, color).is_some_and(|target| {
+                        matches!(target.piece_type, PieceType::King(_))
+                            || target.piece_type.value() > value
+                            || !self.is_attacked(square, &color.opposite())
+                    })
+                })
+                .collect();
+            if targets.len() >= 2 {
+                targets.sort_unstable();
+                motifs.push(Motif::Fork { attacker, targets });
+            }
+        }
+    }
+
+    // Pins, skewers and discovered attacks all look at the first two pieces
+    // along each line a slider moves on
+    fn find_lines(
+        &self,
+        color: &PieceColor,
+        pins: &mut Vec<Motif>,
+        skewers: &mut Vec<Motif>,
+        discovered: &mut Vec<Motif>,
+    ) {
+        let sliders = [
+            (PieceType::Rook(false), &STRAIGHT[..]),
+            (PieceType::Bishop, &DIAGONAL[..]),
+            (PieceType::Queen, &QUEEN[..]),
+        ];
+        for (piece_type, steps) in sliders {
+            for slider in Squares(self.piece_mask(color, piece_type)) {
+                for &step in steps {
+                    let Some(first) = self.next_piece(slider, step) else {
+                        continue;
+                    };
+                    let Some(second) = self.next_piece(first, step) else {
+                        continue;
+                    };
+                    let Some(behind) = self.enemy_at(second, color) else {
+                        continue;
+                    };
+                    let behind_value = behind.piece_type.value();
+                    let behind_is_king = matches!(behind.piece_type, PieceType::King(_));
+                    match self.enemy_at(first, color) {
+                        Some(front)
+                            if behind_is_king || behind_value > front.piece_type.value() =>
+                        {
+                            pins.push(Motif::Pin {
+                                pinner: slider,
+                                pinned: first,
+                                behind: second,
+                                absolute: behind_is_king,
+                            })
+                        }
+                        Some(front) if front.piece_type.value() > behind_value => {
+                            skewers.push(Motif::Skewer {
+                                attacker: slider,
+                                front: first,
+                                behind: second,
+                            })
+                        }
+                        Some(_) => {}
+                        None if behind_is_king || behind_value > self.value_at(slider) => {
+                            discovered.push(Motif::DiscoveredAttack {
+                                slider,
+                                blocker: first,
+                                target: second,
+                            })
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn find_overloaded(&self, color: &PieceColor, motifs: &mut Vec<Motif>) {
+        let kings = self.piece_mask(color, PieceType::King(false));
+        let mut duties: Vec<(usize, usize)> = Squares(self.color_mask(color) & !kings)
+            .filter(|&square| self.is_attacked(square, &color.opposite()))
+            .filter_map(|square| match self.attackers(square, color)[..] {
+                [defender] => Some((defender, square)),
+                _ => None,
+            })
+            .collect();
+        duties.sort_unstable();
+        for chunk in duties.chunk_by(|a, b| a.0 == b.0) {
+            if chunk.len() >= 2 {
+                motifs.push(Motif::Overloaded {
+                    defender: chunk[0].0,
+                    defended: chunk.iter().map(|&(_, square)| square).collect(),
+                });
+            }
+        }
+    }
+
+    // First occupied square from `square` along `step`, if any before the edge
+    fn next_piece(&self, square: usize, step: isize) -> Option<usize> {
+        let mut square = square as isize + step;
+        while Board::is_playing_square(square as usize) {
+            if self.fields()[square as usize].is_some() {
+                return Some(square as usize);
+            }
+            square += step;
+        }
+        None
+    }
+
+    fn enemy_at(&self, square: usize, color: &PieceColor) -> Option<Piece> {
+        self.fields()[square].filter(|piece| piece.piece_color != *color)
+    }
+
+    fn value_at(&self, square: usize) -> i32 {
+        self.fields()[square].map_or(0, |piece| piece.piece_type.value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    fn motifs(fen: &str) -> Vec<Motif> {
+        Position::from_fen(fen).unwrap().board.find_motifs()
+    }
+
+    #[test]
+    fn relative_pin() {
+        // The bishop on b2 pins the knight on d4 to the rook on f6
+        assert_eq!(
+            motifs("7k/8/5r2/8/3n4/8/1B6/7K w - - 0 1"),
+            [Motif::Pin {
+                pinner: 32,
+                pinned: 54,
+                behind: 76,
+                absolute: false
+            }]
+        );
+    }
+
+    #[test]
+    fn skewer() {
+        // The bishop on b2 drives the queen off d4 and takes the rook on f6
+        assert_eq!(
+            motifs("7k/8/5r2/8/3q4/8/1B6/7K w - - 0 1"),
+            [Motif::Skewer {
+                attacker: 32,
+                front: 54,
+                behind: 76
+            }]
+        );
+    }
+
+    #[test]
+    fn discovered_attack() {
+        // Any knight move uncovers the rook on e1 against the king on e8
+        assert_eq!(
+            motifs("4k3/8/8/8/4N3/8/8/4R2K w - - 0 1"),
+            [Motif::DiscoveredAttack {
+                slider: 25,
+                blocker: 55,
+                target: 95
+            }]
+        );
+    }
+
+    #[test]
+    fn overloaded_defender() {
+        // The queen on d7 alone guards both knights the pawns attack
+        assert_eq!(
+            motifs("7k/3q4/2n1n3/1P3P2/8/8/8/7K w - - 0 1"),
+            [Motif::Overloaded {
+                defender: 84,
+                defended: vec![73, 75]
+            }]
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use crate::board::{Board, Piece, PieceColor, PieceType};
+use crate::board::{Board, Piece, PieceColor, PieceType, Squares};
+use crate::error::Error;
+use crate::movelist::MoveList;
+use std::fmt;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Move {
     pub source: usize,
     pub target: usize,
@@ -12,13 +15,22 @@ pub struct Move {
 
 impl Move {
     fn is_on_board(&self) -> bool {
-        !(self.target % 10 == 0
-            || (self.target + 1) % 10 == 0
-            || self.target < 20
-            || self.target > 100)
+        Board::is_playing_square(self.target)
     }
 
-    fn create_move(source: usize, target: usize, captured: Option<Piece>) -> Self {
+    /// Placeholder pushed onto the log when a side passes, e.g. in null-move search.
+    /// It never matches a double pawn push, so no en passant follows it.
+    pub const fn null() -> Self {
+        Self::create_move(0, 0, None)
+    }
+
+    /// Move from `source` to `target` with nothing else known about it, as a user
+    /// would give it. [`Board::try_make_move`] fills in the rest.
+    pub fn new(source: usize, target: usize) -> Self {
+        Self::create_move(source, target, None)
+    }
+
+    const fn create_move(source: usize, target: usize, captured: Option<Piece>) -> Self {
         Self {
             source,
             target,
@@ -30,54 +42,247 @@ impl Move {
     }
 
     fn get_promoted_pawn(&self, color: PieceColor) -> [Move; 4] {
-        let mut p1 = self.clone();
-        p1.promotion = Some(Piece::new("Queen", color));
-        let mut p2 = self.clone();
-        p2.promotion = Some(Piece::new("Knight", color));
-        let mut p3 = self.clone();
-        p3.promotion = Some(Piece::new("Bishop", color));
-        let mut p4 = self.clone();
-        p4.promotion = Some(Piece::new("Rook", color));
+        let mut p1 = *self;
+        p1.promotion = Some(Piece::from_type(PieceType::Queen, color));
+        let mut p2 = *self;
+        p2.promotion = Some(Piece::from_type(PieceType::Knight, color));
+        let mut p3 = *self;
+        p3.promotion = Some(Piece::from_type(PieceType::Bishop, color));
+        let mut p4 = *self;
+        p4.promotion = Some(Piece::from_type(PieceType::Rook(false), color));
         [p1, p2, p3, p4]
     }
 }
 
+/// What a move destroys, returned by [`Board::make_move`] so that
+/// [`Board::undo_move`] can put the board back exactly as it was.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UndoInfo {
+    pub mv: Move,
+    /// The piece that moved, with its moved flag from before the move
+    pub moved: Option<Piece>,
+    pub captured: Option<Piece>,
+    /// Castling rights before the move, see [`Board::castling_rights`]
+    pub castling: [bool; 4],
+    /// En passant square before the move, see [`Board::en_passant`]
+    pub en_passant: Option<usize>,
+    /// Zobrist key before the move, with the moving side to move
+    pub hash: u64,
+    /// Halfmove clock before the move. A bare board doesn't count moves and
+    /// leaves it at 0, [`Position::make_move`](crate::Position::make_move) fills it in.
+    pub halfmove_clock: u32,
+}
+
+/// Long algebraic notation as used by UCI, e.g. `e2e4` or `e7e8q`.
+///
+/// ```
+/// use yauche_game::Position;
+///
+/// let position = Position::default();
+/// let mv = position.parse_move("Nf3").unwrap();
+/// assert_eq!(mv.to_string(), "g1f3");
+/// ```
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Move::null() {
+            return write!(f, "0000");
+        }
+        write!(
+            f,
+            "{}{}",
+            Board::square_name(self.source),
+            Board::square_name(self.target)
+        )?;
+        if let Some(piece) = self.promotion {
+            let symbol = match piece.piece_type {
+                PieceType::Knight => 'n',
+                PieceType::Bishop => 'b',
+                PieceType::Rook(_) => 'r',
+                _ => 'q',
+            };
+            write!(f, "{}", symbol)?;
+        }
+        Ok(())
+    }
+}
+
 impl Board {
-    pub fn perft(&self, depth: usize, log: &Vec<Move>, color: &PieceColor) -> u128{
-        if depth == 0{
-            return 1;
+    /// Name of a mailbox square, e.g. 21 is `a1`. Squares off the board have no
+    /// name and come out as their number in brackets, e.g. `[15]`.
+    pub fn square_name(square: usize) -> String {
+        if !Board::is_playing_square(square) {
+            return format!("[{}]", square);
         }
-        let mut nodes = 0;
+        let file = (b'a' + (square % 10) as u8 - 1) as char;
+        let rank = square / 10 - 1;
+        format!("{}{}", file, rank)
+    }
 
-        let mut board = self.clone();
-        let mut log_copy = log.clone();
+    /// Whether `square` is one of the 64 squares rather than the border around them.
+    /// Pieces anywhere else are ignored by the move generator.
+    pub fn is_playing_square(square: usize) -> bool {
+        (21..=98).contains(&square) && !square.is_multiple_of(10) && square % 10 != 9
+    }
 
-        let moves = self.get_pv_moves(color, log);
-        for pv_move in moves{
-            //let pre_board = board.clone();
-            board.make_move(&pv_move, &mut log_copy);
-            if board.validate(){
-                nodes += board.perft(depth - 1, &log_copy, if *color == PieceColor::Black {&PieceColor::White} else {&PieceColor::Black});
+    /// Mailbox square of a name like `a1`, the inverse of [`Board::square_name`].
+    pub fn square_index(name: &str) -> Option<usize> {
+        match name.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                Some((rank - b'1' + 2) as usize * 10 + (file - b'a' + 1) as usize)
             }
-            board.undo_move(&mut log_copy);
-            //let post_board = board.clone();
-            //if pre_board != post_board{
-            //    dbg!(pre_board.fields.iter().zip(post_board.fields).filter(|p| *p.0 != p.1).collect::<Vec<_>>(),pv_move, &log_copy);
-            //    println!("Current board state is: {}", board);
-            //}
-            //assert_eq!(pre_board, post_board);
+            _ => None,
         }
-        nodes
+    }
 
+    /// Finds the legal move of `color` written as `text` in long algebraic notation.
+    pub fn find_move(&self, text: &str, color: &PieceColor, log: &[Move]) -> Option<Move> {
+        self.get_v_moves(color, log)
+            .into_iter()
+            .find(|m| m.to_string() == text)
     }
 
-    pub fn validate(&self) -> bool {
-        for (position, king) in self.fields.iter().enumerate().filter(|p| match p {
-            (_, Some(q)) => {
-                q.piece_type == PieceType::King(true) || q.piece_type == PieceType::King(false)
+    /// The legal move of `color` that `mv` stands for, with the captured piece, en
+    /// passant and castling filled in from the board. Only the source, the target
+    /// and the type of a promotion piece are looked at.
+    pub fn check_move(&self, mv: &Move, color: &PieceColor, log: &[Move]) -> Result<Move, Error> {
+        let illegal = |reason: String| Err(Error::IllegalMove(format!("{}: {}", mv, reason)));
+        if !Board::is_playing_square(mv.source) || !Board::is_playing_square(mv.target) {
+            return Err(Error::IllegalMove(format!(
+                "{} -> {}: not on the board",
+                mv.source, mv.target
+            )));
+        }
+        let target = Board::square_name(mv.target);
+        let source = Board::square_name(mv.source);
+        match self.fields()[mv.source] {
+            None => return illegal(format!("no piece on {}", source)),
+            Some(piece) if piece.piece_color != *color => {
+                return illegal(format!("the piece on {} isn't {:?}'s", source, color))
             }
-            _ => false,
-        }) {
+            _ => {}
+        }
+        if self.fields()[mv.target].is_some_and(|p| p.piece_color == *color) {
+            return illegal(format!("{} is taken by a {:?} piece", target, color));
+        }
+
+        let same_promotion = |m: &Move| match (m.promotion, mv.promotion) {
+            (Some(a), Some(b)) => {
+                std::mem::discriminant(&a.piece_type) == std::mem::discriminant(&b.piece_type)
+            }
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        let candidates: Vec<Move> = self
+            .get_pv_moves(color, log)
+            .into_iter()
+            .filter(|m| m.source == mv.source && m.target == mv.target)
+            .collect();
+        if candidates.is_empty() {
+            return illegal(format!("the piece on {} can't go to {}", source, target));
+        }
+        let candidate = match candidates.into_iter().find(same_promotion) {
+            Some(candidate) => candidate,
+            None if mv.promotion.is_none() => return illegal("no promotion piece".to_string()),
+            None => return illegal("not a promotion".to_string()),
+        };
+        let mut board = *self;
+        board.apply_move(&candidate, &mut log.to_vec());
+        if !board.validate(color) {
+            return illegal("leaves the king in check".to_string());
+        }
+        Ok(candidate)
+    }
+
+    /// Plays `mv` for `color` after [`Board::check_move`] found it legal, and
+    /// returns the move as played. On error the board and `log` are unchanged.
+    ///
+    /// ```
+    /// use yauche_game::{Board, Move, PieceColor};
+    ///
+    /// let mut board = Board::default();
+    /// let mut log = Vec::new();
+    /// let e4 = board.try_make_move(&Move::new(35, 55), &PieceColor::White, &mut log);
+    /// assert_eq!(e4.unwrap().to_string(), "e2e4");
+    /// assert!(board.try_make_move(&Move::new(84, 54), &PieceColor::Black, &mut log).is_err());
+    /// assert_eq!(log.len(), 1);
+    /// ```
+    pub fn try_make_move(
+        &mut self,
+        mv: &Move,
+        color: &PieceColor,
+        log: &mut Vec<Move>,
+    ) -> Result<Move, Error> {
+        let mv = self.check_move(mv, color, log)?;
+        self.make_move(&mv, log);
+        Ok(mv)
+    }
+
+    /// Which castling moves the kings and rooks still allow, whatever stands in
+    /// between: White king side, White queen side, Black king side and Black
+    /// queen side.
+    pub fn castling_rights(&self) -> [bool; 4] {
+        let unmoved = |king: usize, rook: usize, color: PieceColor| {
+            self.fields()[king] == Some(Piece::from_type(PieceType::King(false), color))
+                && self.fields()[rook] == Some(Piece::from_type(PieceType::Rook(false), color))
+        };
+        [
+            unmoved(25, 28, PieceColor::White),
+            unmoved(25, 21, PieceColor::White),
+            unmoved(95, 98, PieceColor::Black),
+            unmoved(95, 91, PieceColor::Black),
+        ]
+    }
+
+    /// Square a pawn just passed over with a double push, whether it can be
+    /// taken en passant or not.
+    pub fn en_passant(&self, log: &[Move]) -> Option<usize> {
+        let last = log.last()?;
+        let pawn = matches!(
+            self.fields()[last.target].map(|p| p.piece_type),
+            Some(PieceType::Pawn(_))
+        );
+        (pawn && last.source.abs_diff(last.target) == 20).then(|| (last.source + last.target) / 2)
+    }
+
+    /// Counts the legal move sequences `depth` plies deep, the standard check
+    /// of a move generator.
+    ///
+    /// ```
+    /// use yauche_game::{Board, PieceColor};
+    ///
+    /// assert_eq!(Board::default().perft(3, &[], &PieceColor::White), 8902);
+    /// ```
+    pub fn perft(&self, depth: usize, log: &[Move], color: &PieceColor) -> u128 {
+        let mut board = *self;
+        let mut log = log.to_vec();
+        board.perft_from(depth, &mut log, color)
+    }
+
+    // Makes and undoes rather than copies, so that debug builds check every
+    // undo against what the move destroyed
+    fn perft_from(&mut self, depth: usize, log: &mut Vec<Move>, color: &PieceColor) -> u128 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.get_v_moves(color, log);
+        // The moves of the last ply only need counting, not playing
+        if depth == 1 {
+            return moves.len() as u128;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            let undo = self.make_move(&mv, log);
+            nodes += self.perft_from(depth - 1, log, &color.opposite());
+            self.undo_move(&undo, log);
+        }
+        nodes
+    }
+
+    /// Checks that the king of `color` is not left in check.
+    pub fn validate(&self, color: &PieceColor) -> bool {
+        for (position, king) in self
+            .squares(color, PieceType::King(false))
+            .map(|position| (position, &self.fields()[position]))
+        {
             let directions = [10, 1];
             let king = king.unwrap();
 
@@ -89,7 +294,8 @@ impl Board {
                     || position + d < 20
                     || position + d > 100)
                 {
-                    let tmp = Move::create_move(position, position + d, self.fields[position + d]);
+                    let tmp =
+                        Move::create_move(position, position + d, self.fields()[position + d]);
                     if let Some(piece) = tmp.captured {
                         if piece.piece_color != king.piece_color && {
                             piece.piece_type == PieceType::Queen
@@ -110,7 +316,8 @@ impl Board {
                     || position - d < 20
                     || position - d > 100)
                 {
-                    let tmp = Move::create_move(position, position - d, self.fields[position - d]);
+                    let tmp =
+                        Move::create_move(position, position - d, self.fields()[position - d]);
                     if let Some(piece) = tmp.captured {
                         if piece.piece_color != king.piece_color && {
                             piece.piece_type == PieceType::Queen
@@ -137,7 +344,8 @@ impl Board {
                     || position + d < 20
                     || position + d > 100)
                 {
-                    let tmp = Move::create_move(position, position + d, self.fields[position + d]);
+                    let tmp =
+                        Move::create_move(position, position + d, self.fields()[position + d]);
                     if let Some(piece) = tmp.captured {
                         if piece.piece_color != king.piece_color && {
                             piece.piece_type == PieceType::Queen
@@ -156,7 +364,8 @@ impl Board {
                     || position - d < 20
                     || position - d > 100)
                 {
-                    let tmp = Move::create_move(position, position - d, self.fields[position - d]);
+                    let tmp =
+                        Move::create_move(position, position - d, self.fields()[position - d]);
                     if let Some(piece) = tmp.captured {
                         if piece.piece_color != king.piece_color && {
                             piece.piece_type == PieceType::Queen
@@ -181,7 +390,7 @@ impl Board {
                 position + 8,  //LF
             ];
             for target in targets {
-                if let Some(on_target) = self.fields[target] {
+                if let Some(on_target) = self.fields()[target] {
                     //Attack
                     if on_target.piece_color != king.piece_color
                         && on_target.piece_type == PieceType::Knight
@@ -191,67 +400,169 @@ impl Board {
                 }
             }
 
-            // Oh god... Pawn check
-                let step = if king.piece_color == PieceColor::White {
-                    10
-                } else {
-                    -10
-                };
-                let target = (position as isize + 2 * step) as usize;
-
-                //Attacks
-                if let Some(piece) = self.fields[target + 1] {
-                    if piece.piece_color != king.piece_color && (piece.piece_type == PieceType::Pawn(false) || piece.piece_type == PieceType::Pawn(true)) {
+            // King check, the kings can't stand next to each other
+            let targets = [
+                position + 10,
+                position + 11,
+                position + 1,
+                position - 9,
+                position - 10,
+                position - 11,
+                position - 1,
+                position + 9,
+            ];
+            for target in targets {
+                if let Some(on_target) = self.fields()[target] {
+                    if on_target.piece_color != king.piece_color
+                        && matches!(on_target.piece_type, PieceType::King(_))
+                    {
                         return false;
                     }
                 }
+            }
 
-                if let Some(piece) = self.fields[target - 1] {
-                    if piece.piece_color != king.piece_color && (piece.piece_type == PieceType::Pawn(false) || piece.piece_type == PieceType::Pawn(true)) {
-                        return false;
-                    }
+            // Oh god... Pawn check
+            let step = if king.piece_color == PieceColor::White {
+                10
+            } else {
+                -10
+            };
+            let target = (position as isize + step) as usize;
+
+            //Attacks
+            if let Some(piece) = self.fields()[target + 1] {
+                if piece.piece_color != king.piece_color
+                    && (piece.piece_type == PieceType::Pawn(false)
+                        || piece.piece_type == PieceType::Pawn(true))
+                {
+                    return false;
                 }
+            }
 
+            if let Some(piece) = self.fields()[target - 1] {
+                if piece.piece_color != king.piece_color
+                    && (piece.piece_type == PieceType::Pawn(false)
+                        || piece.piece_type == PieceType::Pawn(true))
+                {
+                    return false;
+                }
+            }
         }
         true
     }
 
-    pub fn get_v_moves(&self, color: &PieceColor, log: &Vec<Move>) -> Vec<Move> {
-        let mut log_clone = log.clone();
-        return self.get_pv_moves(color, log).into_iter().filter(|m| {
-            let mut board = self.clone();
-            board.make_move(&m, &mut log_clone);
-            board.validate()
-
-        } ).collect();
-    }
-
-    pub fn get_pv_moves(&self, color: &PieceColor, log: &Vec<Move>) -> Vec<Move> {
-        let mut vec = Vec::new();
-        vec.append(&mut self.get_pawn_moves(color, log));
-        vec.append(&mut self.get_knight_moves(color));
-        vec.append(&mut self.get_bishop_moves(color));
-        vec.append(&mut self.get_rook_moves(color));
-        vec.append(&mut self.get_king_moves(color));
-
-        vec
-    }
-    fn get_king_moves(&self, color: &PieceColor) -> Vec<Move> {
-        let kings = self
-            .fields
-            .iter()
-            .enumerate()
-            .filter(|p| match p {
-                (_, Some(q)) => {
-                    q.piece_color == *color
-                        && (q.piece_type == PieceType::King(true)
-                            || q.piece_type == PieceType::King(false))
+    pub fn in_check(&self, color: &PieceColor) -> bool {
+        !self.validate(color)
+    }
+
+    /// Returns the squares of all pieces of `color` that attack `square`.
+    /// Sliders are only counted if the ray between them and `square` is empty.
+    pub fn attackers(&self, square: usize, color: &PieceColor) -> Vec<usize> {
+        let mut attackers = Vec::new();
+        self.find_attackers(square, color, |attacker| {
+            attackers.push(attacker);
+            false
+        });
+        attackers
+    }
+
+    /// Whether any piece of `color` attacks `square`, without collecting them.
+    pub fn is_attacked(&self, square: usize, color: &PieceColor) -> bool {
+        let mut attacked = false;
+        self.find_attackers(square, color, |_| {
+            attacked = true;
+            true
+        });
+        attacked
+    }
+
+    // Calls `found` with every attacker until it returns true
+    pub(crate) fn find_attackers(
+        &self,
+        square: usize,
+        color: &PieceColor,
+        mut found: impl FnMut(usize) -> bool,
+    ) {
+        let on_board =
+            |pos: isize| (21..=98).contains(&pos) && pos % 10 != 0 && (pos + 1) % 10 != 0;
+        let piece_at = |pos: isize| -> Option<Piece> {
+            if on_board(pos) {
+                self.fields()[pos as usize].filter(|p| p.piece_color == *color)
+            } else {
+                None
+            }
+        };
+        let square = square as isize;
+
+        // Pawns attack diagonally forward, so look one rank behind the square
+        let pawn_step = if *color == PieceColor::White { -10 } else { 10 };
+        for side in [-1, 1] {
+            let pos = square + pawn_step + side;
+            if let Some(piece) = piece_at(pos) {
+                if matches!(piece.piece_type, PieceType::Pawn(_)) && found(pos as usize) {
+                    return;
                 }
-                _ => false,
-            })
-            .map(|(position, king)| {
-                let mut moves: Vec<Move> = Vec::new();
+            }
+        }
+
+        for offset in [19, 21, 12, -8, -19, -21, -12, 8] {
+            let pos = square + offset;
+            if let Some(piece) = piece_at(pos) {
+                if piece.piece_type == PieceType::Knight && found(pos as usize) {
+                    return;
+                }
+            }
+        }
 
+        for offset in [10, 11, 1, -9, -10, -11, -1, 9] {
+            let pos = square + offset;
+            if let Some(piece) = piece_at(pos) {
+                if matches!(piece.piece_type, PieceType::King(_)) && found(pos as usize) {
+                    return;
+                }
+            }
+        }
+
+        for (directions, diagonal) in [([10, -10, 1, -1], false), ([11, -11, 9, -9], true)] {
+            for step in directions {
+                let mut pos = square + step;
+                while on_board(pos) {
+                    if let Some(piece) = self.fields()[pos as usize] {
+                        if piece.piece_color == *color
+                            && match piece.piece_type {
+                                PieceType::Queen => true,
+                                PieceType::Bishop => diagonal,
+                                PieceType::Rook(_) => !diagonal,
+                                _ => false,
+                            }
+                            && found(pos as usize)
+                        {
+                            return;
+                        }
+                        break;
+                    }
+                    pos += step;
+                }
+            }
+        }
+    }
+
+    /// Moves of `color` that follow the rules of movement, some may leave the
+    /// king in check.
+    pub fn get_pv_moves(&self, color: &PieceColor, log: &[Move]) -> MoveList {
+        let mut moves = MoveList::new();
+        self.get_pawn_moves(color, log, &mut moves);
+        self.get_knight_moves(color, &mut moves);
+        self.get_bishop_moves(color, &mut moves);
+        self.get_rook_moves(color, &mut moves);
+        self.get_king_moves(color, &mut moves);
+        moves
+    }
+
+    fn get_king_moves(&self, color: &PieceColor, moves: &mut MoveList) {
+        Squares(self.piece_mask(color, PieceType::King(false)))
+            .map(|position| (position, &self.fields()[position]))
+            .for_each(|(position, king)| {
                 let king = king.unwrap();
 
                 let targets = [
@@ -265,7 +576,7 @@ impl Board {
                     position + 9,
                 ];
                 for target in targets {
-                    if let Some(on_target) = self.fields[target] {
+                    if let Some(on_target) = self.fields()[target] {
                         //Attack
                         if on_target.piece_color != king.piece_color {
                             let tmp = Move::create_move(position, target, Some(on_target));
@@ -281,185 +592,146 @@ impl Board {
                 }
 
                 //Castling
-                if king.piece_type == PieceType::King(false) {
-                    if let Some(rook) = self.fields[position - 4] {
+                let enemy = king.piece_color.opposite();
+                let safe = |square: usize| !self.is_attacked(square, &enemy);
+                if king.piece_type == PieceType::King(false) && safe(position) {
+                    if let Some(rook) = self.fields()[position - 4] {
                         if rook.piece_type == PieceType::Rook(false)
-                            && self.fields[position - 1].is_none()
-                            && self.fields[position - 2].is_none()
-                            && self.fields[position - 3].is_none()
+                            && rook.piece_color == king.piece_color
+                            && self.fields()[position - 1].is_none()
+                            && self.fields()[position - 2].is_none()
+                            && self.fields()[position - 3].is_none()
+                            && safe(position - 1)
                         {
-                            let mut tmp = Move::create_move(position, position - 4, None);
-                            tmp.castle = Some((true, rook));
+                            let mut tmp = Move::create_move(position, position - 2, None);
+                            tmp.castle = Some((false, rook));
                             moves.push(tmp);
                         }
                     }
 
-                    if let Some(rook) = self.fields[position + 3] {
+                    if let Some(rook) = self.fields()[position + 3] {
                         if rook.piece_type == PieceType::Rook(false)
-                            && self.fields[position + 1].is_none()
-                            && self.fields[position + 2].is_none()
+                            && rook.piece_color == king.piece_color
+                            && self.fields()[position + 1].is_none()
+                            && self.fields()[position + 2].is_none()
+                            && safe(position + 1)
                         {
-                            let mut tmp = Move::create_move(position, position + 3, None);
+                            let mut tmp = Move::create_move(position, position + 2, None);
                             tmp.castle = Some((true, rook));
                             moves.push(tmp);
                         }
                     }
                 }
-
-                moves
-            })
-            .fold(Vec::new(), |mut acc, mut v| {
-                acc.append(&mut v);
-                acc
             });
-        kings
-    }
-    fn get_rook_moves(&self, color: &PieceColor) -> Vec<Move> {
-        let knights = self
-            .fields
-            .iter()
-            .enumerate()
-            .filter(|p| match p {
-                (_, Some(q)) => {
-                    q.piece_color == *color
-                        && (q.piece_type == PieceType::Rook(true)
-                            || q.piece_type == PieceType::Rook(false)
-                            || q.piece_type == PieceType::Queen)
-                }
-                _ => false,
-            })
-            .map(|(position, rook)| {
-                let mut moves: Vec<Move> = Vec::new();
-                let rook = rook.unwrap();
-
-                let directions = [10, 1];
-
-                for step in directions {
-                    let mut d = step;
-                    while !((position + d) % 10 == 0
-                        || (position + d + 1) % 10 == 0
-                        || position + d < 20
-                        || position + d > 100)
-                    {
-                        let tmp =
-                            Move::create_move(position, position + d, self.fields[position + d]);
-                        if let Some(piece) = tmp.captured {
-                            if piece.piece_color != rook.piece_color {
-                                moves.push(tmp);
-                            }
-                            break;
-                        } else {
+    }
+    fn get_rook_moves(&self, color: &PieceColor, moves: &mut MoveList) {
+        Squares(
+            self.piece_mask(color, PieceType::Rook(false))
+                | self.piece_mask(color, PieceType::Queen),
+        )
+        .map(|position| (position, &self.fields()[position]))
+        .for_each(|(position, rook)| {
+            let rook = rook.unwrap();
+
+            let directions = [10, 1];
+
+            for step in directions {
+                let mut d = step;
+                while !((position + d) % 10 == 0
+                    || (position + d + 1) % 10 == 0
+                    || position + d < 20
+                    || position + d > 100)
+                {
+                    let tmp =
+                        Move::create_move(position, position + d, self.fields()[position + d]);
+                    if let Some(piece) = tmp.captured {
+                        if piece.piece_color != rook.piece_color {
                             moves.push(tmp);
                         }
-                        d += step;
+                        break;
+                    } else {
+                        moves.push(tmp);
                     }
+                    d += step;
+                }
 
-                    d = step;
-                    while !((position - d) % 10 == 0
-                        || (position - d + 1) % 10 == 0
-                        || position - d < 20
-                        || position - d > 100)
-                    {
-                        let tmp =
-                            Move::create_move(position, position - d, self.fields[position - d]);
-                        if let Some(piece) = tmp.captured {
-                            if piece.piece_color != rook.piece_color {
-                                moves.push(tmp);
-                            }
-                            break;
-                        } else {
+                d = step;
+                while !((position - d) % 10 == 0
+                    || (position - d + 1) % 10 == 0
+                    || position - d < 20
+                    || position - d > 100)
+                {
+                    let tmp =
+                        Move::create_move(position, position - d, self.fields()[position - d]);
+                    if let Some(piece) = tmp.captured {
+                        if piece.piece_color != rook.piece_color {
                             moves.push(tmp);
                         }
-                        d += step;
+                        break;
+                    } else {
+                        moves.push(tmp);
                     }
+                    d += step;
                 }
+            }
+        });
+    }
+    fn get_bishop_moves(&self, color: &PieceColor, moves: &mut MoveList) {
+        Squares(
+            self.piece_mask(color, PieceType::Bishop) | self.piece_mask(color, PieceType::Queen),
+        )
+        .map(|position| (position, &self.fields()[position]))
+        .for_each(|(position, bishop)| {
+            let bishop = bishop.unwrap();
 
-                moves
-            })
-            .fold(Vec::new(), |mut acc, mut v| {
-                acc.append(&mut v);
-                acc
-            });
-        knights
-    }
-    fn get_bishop_moves(&self, color: &PieceColor) -> Vec<Move> {
-        let knights = self
-            .fields
-            .iter()
-            .enumerate()
-            .filter(|p| match p {
-                (_, Some(q)) => {
-                    q.piece_color == *color
-                        && (q.piece_type == PieceType::Bishop || q.piece_type == PieceType::Queen)
-                }
-                _ => false,
-            })
-            .map(|(position, bishop)| {
-                let mut moves: Vec<Move> = Vec::new();
-                let bishop = bishop.unwrap();
-
-                let directions = [11, 9];
-
-                for step in directions {
-                    let mut d = step;
-                    while !((position + d) % 10 == 0
-                        || (position + d + 1) % 10 == 0
-                        || position + d < 20
-                        || position + d > 100)
-                    {
-                        let tmp =
-                            Move::create_move(position, position + d, self.fields[position + d]);
-                        if let Some(piece) = tmp.captured {
-                            if piece.piece_color != bishop.piece_color {
-                                moves.push(tmp);
-                            }
-                            break;
-                        } else {
+            let directions = [11, 9];
+
+            for step in directions {
+                let mut d = step;
+                while !((position + d) % 10 == 0
+                    || (position + d + 1) % 10 == 0
+                    || position + d < 20
+                    || position + d > 100)
+                {
+                    let tmp =
+                        Move::create_move(position, position + d, self.fields()[position + d]);
+                    if let Some(piece) = tmp.captured {
+                        if piece.piece_color != bishop.piece_color {
                             moves.push(tmp);
                         }
-                        d += step;
+                        break;
+                    } else {
+                        moves.push(tmp);
                     }
+                    d += step;
+                }
 
-                    d = step;
-                    while !((position - d) % 10 == 0
-                        || (position - d + 1) % 10 == 0
-                        || position - d < 20
-                        || position - d > 100)
-                    {
-                        let tmp =
-                            Move::create_move(position, position - d, self.fields[position - d]);
-                        if let Some(piece) = tmp.captured {
-                            if piece.piece_color != bishop.piece_color {
-                                moves.push(tmp);
-                            }
-                            break;
-                        } else {
+                d = step;
+                while !((position - d) % 10 == 0
+                    || (position - d + 1) % 10 == 0
+                    || position - d < 20
+                    || position - d > 100)
+                {
+                    let tmp =
+                        Move::create_move(position, position - d, self.fields()[position - d]);
+                    if let Some(piece) = tmp.captured {
+                        if piece.piece_color != bishop.piece_color {
                             moves.push(tmp);
                         }
-                        d += step;
+                        break;
+                    } else {
+                        moves.push(tmp);
                     }
+                    d += step;
                 }
+            }
+        });
+    }
 
-                moves
-            })
-            .fold(Vec::new(), |mut acc, mut v| {
-                acc.append(&mut v);
-                acc
-            });
-        knights
-    }
-
-    fn get_knight_moves(&self, color: &PieceColor) -> Vec<Move> {
-        let knights = self
-            .fields
-            .iter()
-            .enumerate()
-            .filter(|p| match p {
-                (_, Some(q)) => q.piece_color == *color && q.piece_type == PieceType::Knight,
-                _ => false,
-            })
-            .map(|(position, knight)| {
-                let mut moves: Vec<Move> = Vec::new();
+    fn get_knight_moves(&self, color: &PieceColor, moves: &mut MoveList) {
+        Squares(self.piece_mask(color, PieceType::Knight))
+            .map(|position| (position, &self.fields()[position]))
+            .for_each(|(position, knight)| {
                 let knight = knight.unwrap();
 
                 let targets = [
@@ -473,7 +745,7 @@ impl Board {
                     position + 8,  //LF
                 ];
                 for target in targets {
-                    if let Some(on_target) = self.fields[target] {
+                    if let Some(on_target) = self.fields()[target] {
                         //Attack
                         if on_target.piece_color != knight.piece_color {
                             let tmp = Move::create_move(position, target, Some(on_target));
@@ -487,31 +759,12 @@ impl Board {
                         }
                     }
                 }
-
-                moves
-            })
-            .fold(Vec::new(), |mut acc, mut v| {
-                acc.append(&mut v);
-                acc
             });
-        knights
-    }
-    fn get_pawn_moves(&self, color: &PieceColor, log: &Vec<Move>) -> Vec<Move> {
-        let pawns = self
-            .fields
-            .iter()
-            .enumerate()
-            .filter(|p| match p {
-                (_, Some(q)) => {
-                    q.piece_color == *color
-                       // && (q.piece_type == PieceType::Pawn(true)
-                       //     || q.piece_type == PieceType::Pawn(false))
-                       && if let PieceType::Pawn(_) = q.piece_type {true} else {false}
-                }
-                _ => false,
-            })
-            .map(|(position, pawn)| {
-                let mut moves: Vec<Move> = Vec::new();
+    }
+    fn get_pawn_moves(&self, color: &PieceColor, log: &[Move], moves: &mut MoveList) {
+        Squares(self.piece_mask(color, PieceType::Pawn(false)))
+            .map(|position| (position, &self.fields()[position]))
+            .for_each(|(position, pawn)| {
                 let step = if pawn.unwrap().piece_color == PieceColor::White {
                     10
                 } else {
@@ -519,11 +772,13 @@ impl Board {
                 };
                 //Forward moves
 
-                //target for first move
+                //target for first move, the square passed over has to be empty too
+                let passed = (position as isize + step) as usize;
                 let target = (position as isize + 2 * step) as usize;
-                if self.fields[target].is_none()
-                    && (self.fields[target - 10].is_none() && *color == PieceColor::White || self.fields[target + 10].is_none() && *color == PieceColor::Black)
-                    && pawn.unwrap().piece_type == PieceType::Pawn(false)
+                if pawn.unwrap().piece_type == PieceType::Pawn(false)
+                    && Board::is_playing_square(target)
+                    && self.fields()[passed].is_none()
+                    && self.fields()[target].is_none()
                 {
                     let tmp = Move::create_move(position, target, None);
                     moves.push(tmp);
@@ -532,7 +787,7 @@ impl Board {
                 //target for most of the moves
                 let target = (position as isize + step) as usize;
 
-                if let None = self.fields[target] {
+                if self.fields()[target].is_none() {
                     let tmp = Move::create_move(position, target, None);
                     if tmp.is_on_board()
                         && ((*color == PieceColor::White && tmp.target > 90)
@@ -545,7 +800,7 @@ impl Board {
                 }
 
                 //Attacks
-                if let Some(piece) = self.fields[target + 1] {
+                if let Some(piece) = self.fields()[target + 1] {
                     if piece.piece_color != *color {
                         let tmp = Move::create_move(position, target + 1, Some(piece));
                         if tmp.is_on_board()
@@ -561,7 +816,7 @@ impl Board {
                     }
                 }
 
-                if let Some(piece) = self.fields[target - 1] {
+                if let Some(piece) = self.fields()[target - 1] {
                     if piece.piece_color != *color {
                         let tmp = Move::create_move(position, target - 1, Some(piece));
                         if tmp.is_on_board()
@@ -586,7 +841,7 @@ impl Board {
                             && last_move.source > 80
                             && last_move.target < 70)
                     {
-                        if let Some(pawn) = self.fields[last_move.target] {
+                        if let Some(pawn) = self.fields()[last_move.target] {
                             if let PieceType::Pawn(_) = pawn.piece_type {
                                 if position + 1 == last_move.target {
                                     let mut tmp =
@@ -603,87 +858,132 @@ impl Board {
                         }
                     }
                 }
-
-                moves
-            })
-            .fold(Vec::new(), |mut acc, mut v| {
-                acc.append(&mut v);
-                acc
             });
-        pawns
     }
 
-    pub fn make_move(&mut self, mv: &Move, log: &mut Vec<Move>) {
-        if let Some(_) = mv.promotion {
-            self.fields[mv.source] = None;
-            self.fields[mv.target] = mv.promotion;
-        } else if let Some(pos) = mv.ep {
-            self.fields[pos] = None;
-            self.fields[mv.target] = self.fields[mv.source];
-            self.fields[mv.source] = None;
-        } else if let Some((king_side, rook)) = mv.castle {
-            self.fields[mv.target] = self.fields[mv.source];
-            self.fields[mv.source] = None;
-            if king_side {
-                self.fields[mv.source + 1] = Some(rook);
-                self.fields[mv.source + 3] = None;
-            } else {
-                self.fields[mv.source - 1] = Some(rook);
-                self.fields[mv.source - 4] = None;
-            }
-        } else {
-            if let Some(mut p) = self.fields[mv.source] {
-                match p.piece_type {
-                    PieceType::Pawn(false) => p.piece_type = PieceType::Pawn(true),
-                    _ => (),
-                }
-                self.fields[mv.source] = Some(p);
-            }
-            self.fields[mv.target] = self.fields[mv.source];
-            self.fields[mv.source] = None;
+    /// Plays `mv`, which must come from the move generator, and appends it to `log`.
+    /// Returns what [`Board::undo_move`] needs to take it back.
+    ///
+    /// ```
+    /// use yauche_game::{Board, PieceColor};
+    ///
+    /// let mut board = Board::default();
+    /// let mut log = Vec::new();
+    /// let e4 = board.find_move("e2e4", &PieceColor::White, &log).unwrap();
+    /// let undo = board.make_move(&e4, &mut log);
+    /// assert!(board.fields()[55].is_some());
+    /// board.undo_move(&undo, &mut log);
+    /// assert_eq!(board, Board::default());
+    /// assert!(log.is_empty());
+    /// ```
+    pub fn make_move(&mut self, mv: &Move, log: &mut Vec<Move>) -> UndoInfo {
+        let moved = self.fields()[mv.source];
+        let color = moved.map_or(PieceColor::White, |p| p.piece_color);
+        let undo = UndoInfo {
+            mv: *mv,
+            moved,
+            captured: self.fields()[mv.ep.unwrap_or(mv.target)],
+            castling: self.castling_rights(),
+            en_passant: self.en_passant(log),
+            hash: self.hash(&color, log),
+            halfmove_clock: 0,
         };
+        self.apply_move(mv, log);
+        undo
+    }
+
+    /// Plays `mv` and appends it to `log` like [`Board::make_move`], without
+    /// recording how to take it back. Cheaper for callers that copy the board
+    /// before each move instead of undoing it.
+    pub fn apply_move(&mut self, mv: &Move, log: &mut Vec<Move>) {
+        self.move_pieces(mv);
         log.push(*mv);
     }
 
-    pub fn undo_move(&mut self, log: &mut Vec<Move>) {
-        let mv = log.pop().unwrap();
-        if let Some(p) = mv.promotion {
-            self.fields[mv.source] = Some(Piece {
-                piece_type: PieceType::Pawn(true),
-                piece_color: p.piece_color,
-            });
-            self.fields[mv.target] = mv.captured;
+    pub(crate) fn move_pieces(&mut self, mv: &Move) {
+        if mv.promotion.is_some() {
+            self.set(mv.source, None);
+            self.set(mv.target, mv.promotion);
         } else if let Some(pos) = mv.ep {
-            self.fields[pos] = mv.captured;
-            self.fields[mv.source] = self.fields[mv.target];
-            self.fields[mv.target] = None;
+            self.set(pos, None);
+            self.set(mv.target, self.fields()[mv.source]);
+            self.set(mv.source, None);
         } else if let Some((king_side, rook)) = mv.castle {
-            self.fields[mv.source] = self.fields[mv.target];
-            self.fields[mv.target] = None;
+            let king = self.fields()[mv.source].map(|k| Piece {
+                piece_type: PieceType::King(true),
+                ..k
+            });
+            let rook = Some(Piece {
+                piece_type: PieceType::Rook(true),
+                ..rook
+            });
+            self.set(mv.target, king);
+            self.set(mv.source, None);
             if king_side {
-                self.fields[mv.source + 3] = Some(rook);
-                self.fields[mv.source + 1] = None;
+                self.set(mv.source + 1, rook);
+                self.set(mv.source + 3, None);
             } else {
-                self.fields[mv.source - 4] = Some(rook);
-                self.fields[mv.source - 1] = None;
+                self.set(mv.source - 1, rook);
+                self.set(mv.source - 4, None);
             }
         } else {
-            if let Some(mut p) = self.fields[mv.target] {
-                if p.piece_type == PieceType::Pawn(true)
-                    && ((mv.source < 40 && p.piece_color == PieceColor::White)
-                        || (mv.source > 80 && p.piece_color == PieceColor::Black))
-                {
-                    p.piece_type = PieceType::Pawn(false);
-                    self.fields[mv.source] = Some(p);
-                }
-                else {
-                    self.fields[mv.source] = self.fields[mv.target];
-                }
-            }
-            else{
-                self.fields[mv.source] = self.fields[mv.target];
+            if let Some(mut p) = self.fields()[mv.source] {
+                p.piece_type = match p.piece_type {
+                    PieceType::Pawn(false) => PieceType::Pawn(true),
+                    PieceType::King(false) => PieceType::King(true),
+                    PieceType::Rook(false) => PieceType::Rook(true),
+                    other => other,
+                };
+                self.set(mv.source, Some(p));
             }
-            self.fields[mv.target] = mv.captured;
+            self.set(mv.target, self.fields()[mv.source]);
+            self.set(mv.source, None);
         };
     }
+
+    /// Takes back the move `undo` was returned for, which has to be the last one
+    /// in `log`. Everything the move changed is restored exactly, including the
+    /// moved flags behind the castling rights.
+    pub fn undo_move(&mut self, undo: &UndoInfo, log: &mut Vec<Move>) {
+        let mv = undo.mv;
+        let last = log.pop();
+        debug_assert_eq!(last, Some(mv));
+        self.set(mv.target, None);
+        self.set(mv.ep.unwrap_or(mv.target), undo.captured);
+        if let Some((king_side, rook)) = mv.castle {
+            let (home, castled) = if king_side {
+                (mv.source + 3, mv.source + 1)
+            } else {
+                (mv.source - 4, mv.source - 1)
+            };
+            self.set(castled, None);
+            self.set(home, Some(rook));
+        }
+        self.set(mv.source, undo.moved);
+
+        debug_assert_eq!(self.castling_rights(), undo.castling);
+        debug_assert_eq!(self.en_passant(log), undo.en_passant);
+        debug_assert_eq!(
+            self.hash(
+                &undo.moved.map_or(PieceColor::White, |p| p.piece_color),
+                log
+            ),
+            undo.hash
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_off_the_board_are_named_by_number() {
+        assert_eq!(Board::square_name(21), "a1");
+        assert_eq!(Board::square_name(98), "h8");
+        for square in [0, 20, 29, 99, 119, 500] {
+            assert_eq!(Board::square_name(square), format!("[{}]", square));
+        }
+        assert_eq!(Move::new(15, 35).to_string(), "[15]e2");
+    }
 }
@@ -0,0 +1,133 @@
+use crate::board::{Board, PieceType};
+use crate::error::Error;
+use crate::moves::Move;
+use crate::position::Position;
+
+impl Position {
+    /// Writes a legal move in Standard Algebraic Notation, e.g. `Nf3`, `exd5`,
+    /// `O-O` or `e8=Q#`, with just enough of the source square to be unambiguous.
+    ///
+    /// ```
+    /// use yauche_game::Position;
+    ///
+    /// let position = Position::from_fen("6k1/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+    /// let mv = position.parse_move("a1a8").unwrap();
+    /// assert_eq!(position.to_san(&mv), "Ra8+");
+    /// let mv = position.parse_move("e1c1").unwrap();
+    /// assert_eq!(position.to_san(&mv), "O-O-O");
+    /// ```
+    pub fn to_san(&self, mv: &Move) -> String {
+        let mut san = match mv.castle {
+            Some((true, _)) => "O-O".to_string(),
+            Some((false, _)) => "O-O-O".to_string(),
+            None => self.piece_san(mv),
+        };
+
+        let mut after = self.clone();
+        after.make_move(mv);
+        if after.board.in_check(&after.side) {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    fn piece_san(&self, mv: &Move) -> String {
+        let piece_type = match self.board.fields()[mv.source] {
+            Some(piece) => piece.piece_type,
+            None => return mv.to_string(),
+        };
+        let target = Board::square_name(mv.target);
+        let file = |square: usize| (b'a' + (square % 10) as u8 - 1) as char;
+        let rank = |square: usize| (b'0' + (square / 10) as u8 - 1) as char;
+
+        let mut san = String::new();
+        if let PieceType::Pawn(_) = piece_type {
+            if mv.captured.is_some() {
+                san.push(file(mv.source));
+                san.push('x');
+            }
+            san.push_str(&target);
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(piece_letter(&promotion.piece_type));
+            }
+            return san;
+        }
+
+        san.push(piece_letter(&piece_type));
+        // Other pieces of the same kind that could go to the same square
+        let rivals: Vec<usize> = self
+            .legal_moves()
+            .iter()
+            .filter(|m| {
+                m.target == mv.target
+                    && m.source != mv.source
+                    && self.board.fields()[m.source].map(|p| p.piece_type) == Some(piece_type)
+            })
+            .map(|m| m.source)
+            .collect();
+        if !rivals.is_empty() {
+            if rivals.iter().all(|&s| s % 10 != mv.source % 10) {
+                san.push(file(mv.source));
+            } else if rivals.iter().all(|&s| s / 10 != mv.source / 10) {
+                san.push(rank(mv.source));
+            } else {
+                san.push(file(mv.source));
+                san.push(rank(mv.source));
+            }
+        }
+        if mv.captured.is_some() {
+            san.push('x');
+        }
+        san.push_str(&target);
+        san
+    }
+
+    /// Finds the legal move written as `text` in Standard Algebraic Notation.
+    /// Check marks, annotations like `!?` and a missing `=` before the promotion
+    /// piece are tolerated, and castling may be written with zeros.
+    pub fn parse_san(&self, text: &str) -> Option<Move> {
+        let wanted = normalize_san(text);
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| normalize_san(&self.to_san(mv)) == wanted)
+    }
+
+    /// Finds the legal move written as `text` in either SAN or the long algebraic
+    /// notation of UCI.
+    ///
+    /// ```
+    /// use yauche_game::Position;
+    ///
+    /// let position = Position::default();
+    /// assert_eq!(position.parse_move("Nc3"), position.parse_move("b1c3"));
+    /// assert!(position.parse_move("Ke2").is_err());
+    /// ```
+    pub fn parse_move(&self, text: &str) -> Result<Move, Error> {
+        self.find_move(text)
+            .or_else(|| self.parse_san(text))
+            .ok_or_else(|| Error::IllegalMove(text.to_string()))
+    }
+}
+
+fn piece_letter(piece_type: &PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn(_) => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook(_) => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King(_) => 'K',
+    }
+}
+
+fn normalize_san(text: &str) -> String {
+    text.chars()
+        .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | '='))
+        .map(|c| if c == '0' { 'O' } else { c })
+        .collect()
+}
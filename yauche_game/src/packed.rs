@@ -0,0 +1,152 @@
+use crate::board::{Board, Piece, PieceType};
+use crate::moves::Move;
+
+// Kinds of move in the top four bits, bit 2 marking captures and bit 3
+// promotions, the low two bits of a promotion giving the piece
+const QUIET: u16 = 0;
+const KING_CASTLE: u16 = 2;
+const QUEEN_CASTLE: u16 = 3;
+const CAPTURE: u16 = 4;
+const EN_PASSANT: u16 = 5;
+const PROMOTION: u16 = 8;
+
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook(false),
+    PieceType::Queen,
+];
+
+/// A [`Move`] in 16 bits, small enough for transposition table entries,
+/// killer tables and book files: the source and target square numbered 0 for
+/// `a1` to 63 for `h8`, and four bits for promotion, en passant, castling and
+/// capture. The pieces involved aren't stored, [`PackedMove::unpack`] takes them
+/// from the board. A move with a square off the board packs to
+/// [`PackedMove::NULL`].
+///
+/// ```
+/// use yauche_game::{PackedMove, Position};
+///
+/// let position = Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+/// for mv in position.legal_moves() {
+///     let packed = PackedMove::from(mv);
+///     assert_eq!(packed.unpack(&position.board), mv);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PackedMove(pub u16);
+
+impl PackedMove {
+    /// What [`Move::null`] packs to.
+    pub const NULL: PackedMove = PackedMove(0);
+
+    pub fn source(&self) -> usize {
+        mailbox(self.0 & 0x3F)
+    }
+
+    pub fn target(&self) -> usize {
+        mailbox((self.0 >> 6) & 0x3F)
+    }
+
+    fn flags(&self) -> u16 {
+        self.0 >> 12
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.flags() & CAPTURE != 0
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        self.flags() & PROMOTION != 0
+    }
+
+    /// The full move on `board`, the position it was packed in, with the
+    /// captured piece, promoted piece and castling rook filled in from there.
+    pub fn unpack(&self, board: &Board) -> Move {
+        if *self == PackedMove::NULL {
+            return Move::null();
+        }
+        let source = self.source();
+        let target = self.target();
+        let color = board.fields()[source].unwrap_or_default().piece_color;
+        let mut mv = Move::new(source, target);
+        match self.flags() {
+            KING_CASTLE => mv.castle = board.fields()[source + 3].map(|rook| (true, rook)),
+            QUEEN_CASTLE => mv.castle = board.fields()[source - 4].map(|rook| (false, rook)),
+            EN_PASSANT => {
+                // The pawn taken stands next to the capturing one
+                let ep = source - source % 10 + target % 10;
+                mv.ep = Some(ep);
+                mv.captured = board.fields()[ep];
+            }
+            flags => {
+                if flags & CAPTURE != 0 {
+                    mv.captured = board.fields()[target];
+                }
+                if flags & PROMOTION != 0 {
+                    let piece_type = PROMOTIONS[(flags & 0x3) as usize];
+                    mv.promotion = Some(Piece::from_type(piece_type, color));
+                }
+            }
+        }
+        mv
+    }
+}
+
+impl From<Move> for PackedMove {
+    fn from(mv: Move) -> Self {
+        if mv == Move::null()
+            || !Board::is_playing_square(mv.source)
+            || !Board::is_playing_square(mv.target)
+        {
+            return PackedMove::NULL;
+        }
+        let flags = match (mv.castle, mv.ep, mv.promotion) {
+            (Some((true, _)), _, _) => KING_CASTLE,
+            (Some((false, _)), _, _) => QUEEN_CASTLE,
+            (_, Some(_), _) => EN_PASSANT,
+            (_, _, promotion) => {
+                let capture = if mv.captured.is_some() {
+                    CAPTURE
+                } else {
+                    QUIET
+                };
+                let promotion = promotion.map_or(0, |piece| {
+                    let index = match piece.piece_type {
+                        PieceType::Knight => 0,
+                        PieceType::Bishop => 1,
+                        PieceType::Rook(_) => 2,
+                        _ => 3,
+                    };
+                    PROMOTION | index
+                });
+                capture | promotion
+            }
+        };
+        PackedMove(index(mv.source) | index(mv.target) << 6 | flags << 12)
+    }
+}
+
+// Square numbered 0 to 63 from its mailbox number and back. `square` must be
+// one of the 64 playing squares.
+pub(crate) fn index(square: usize) -> u16 {
+    debug_assert!(Board::is_playing_square(square), "square {}", square);
+    ((square / 10 - 2) * 8 + square % 10 - 1) as u16
+}
+
+pub(crate) fn mailbox(index: u16) -> usize {
+    let index = index as usize;
+    21 + index / 8 * 10 + index % 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_off_the_board_pack_to_null() {
+        assert_eq!(PackedMove::from(Move::new(15, 35)), PackedMove::NULL);
+        assert_eq!(PackedMove::from(Move::new(35, 119)), PackedMove::NULL);
+        assert_ne!(PackedMove::from(Move::new(35, 55)), PackedMove::NULL);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::board::PieceColor;
+use crate::error::Error;
+use crate::moves::Move;
+use crate::position::Position;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const LINE_WIDTH: usize = 79;
+
+/// A game in Portable Game Notation: tag pairs, the position it started from and
+/// the moves played. Writing it gives the PGN text with moves in SAN.
+#[derive(Debug, Clone)]
+pub struct Pgn {
+    /// Tag pairs in the order they are written, the Seven Tag Roster first
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+}
+
+impl Pgn {
+    /// Game from `start` with the Seven Tag Roster filled with placeholders and
+    /// today's date.
+    pub fn new(start: Position) -> Self {
+        let tags = [
+            ("Event", "?".to_string()),
+            ("Site", "?".to_string()),
+            ("Date", today()),
+            ("Round", "?".to_string()),
+            ("White", "?".to_string()),
+            ("Black", "?".to_string()),
+            ("Result", "*".to_string()),
+        ];
+        Self {
+            tags: tags.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            start,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Sets the tag `name`, adding it after the others if it is new.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Reads the first game of a PGN text. Comments, variations and annotation
+    /// glyphs are skipped. Fails if a tag, the starting FEN or a move can't be
+    /// read.
+    ///
+    /// ```
+    /// use yauche_game::pgn::Pgn;
+    ///
+    /// let pgn = Pgn::parse("[White \"Morphy\"]\n\n1. e4 e5 {Open game} 2. Nf3 (2. f4) d6 *").unwrap();
+    /// assert_eq!(pgn.tag("White"), Some("Morphy"));
+    /// assert_eq!(pgn.moves.len(), 4);
+    /// assert_eq!(pgn.position_at(2).unwrap().to_san(&pgn.moves[2]), "Nf3");
+    /// ```
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut tags = Vec::new();
+        let mut lines = text.lines().map(str::trim).peekable();
+        while let Some(line) = lines.next_if(|l| l.is_empty() || l.starts_with('[')) {
+            if line.is_empty() {
+                if tags.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            let (name, value) = line
+                .strip_prefix('[')
+                .and_then(|l| l.strip_suffix(']'))
+                .and_then(|l| l.split_once(' '))
+                .and_then(|(name, value)| {
+                    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+                    Some((name, value))
+                })
+                .ok_or_else(|| Error::InvalidPgn(format!("bad tag {}", line)))?;
+            tags.push((
+                name.to_string(),
+                value.replace("\\\"", "\"").replace("\\\\", "\\"),
+            ));
+        }
+
+        let start = match tags.iter().find(|(n, _)| n == "FEN") {
+            Some((_, fen)) => Position::from_fen(fen)?,
+            None => Position::default(),
+        };
+        let mut pgn = Pgn {
+            tags,
+            start: start.clone(),
+            moves: Vec::new(),
+        };
+        let mut position = start;
+        let movetext: Vec<&str> = lines.take_while(|l| !l.starts_with('[')).collect();
+        for token in tokens(&movetext.join("\n")) {
+            if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                break;
+            }
+            let mv = position.parse_move(&token)?;
+            position.make_move(&mv);
+            pgn.moves.push(mv);
+        }
+        Ok(pgn)
+    }
+
+    /// Position after the first `ply` moves, `None` if the game is shorter.
+    pub fn position_at(&self, ply: usize) -> Option<Position> {
+        let mut position = self.start.clone();
+        for mv in self.moves.get(..ply)? {
+            position.make_move(mv);
+        }
+        Some(position)
+    }
+}
+
+impl fmt::Display for Pgn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(
+                f,
+                "[{} \"{}\"]",
+                name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            )?;
+        }
+        let fen = self.start.to_fen();
+        if fen != START_FEN {
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{}\"]", fen)?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::new();
+        let mut position = self.start.clone();
+        for (i, mv) in self.moves.iter().enumerate() {
+            let number = position.fullmove_number;
+            match position.side {
+                PieceColor::White => tokens.push(format!("{}.", number)),
+                _ if i == 0 => tokens.push(format!("{}...", number)),
+                _ => (),
+            }
+            tokens.push(position.to_san(mv));
+            position.make_move(mv);
+        }
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+/// Today's date as PGN writes it, e.g. `2021.03.14`.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_secs() / 86_400) as i64;
+    // Civil date from days since 1970-01-01, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Moves and results of a movetext, without move numbers, comments, variations
+/// and annotations.
+fn tokens(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut depth: usize = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                chars.by_ref().find(|&c| c == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 && !c.is_whitespace() => {
+                current.push(c);
+                continue;
+            }
+            _ => (),
+        }
+        push_token(&mut tokens, &mut current);
+    }
+    push_token(&mut tokens, &mut current);
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<String>, current: &mut String) {
+    // Move numbers may stick to the move, as in `1.e4` or `12...Nf6`
+    let token = match current.rfind('.') {
+        Some(dot) => &current[dot + 1..],
+        None => current.as_str(),
+    };
+    if !token.is_empty() && !token.starts_with('$') {
+        tokens.push(token.to_string());
+    }
+    current.clear();
+}
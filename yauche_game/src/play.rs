@@ -0,0 +1,346 @@
+use crate::board::{PieceColor, PieceType};
+use crate::engine::Engine;
+use crate::moves::Move;
+use crate::pgn::Pgn;
+use crate::position::Position;
+use crate::render::Renderer;
+use crate::search::Limits;
+use crate::time::TimeControl;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+const HELP: &str = "\
+Enter moves in SAN (Nf3, exd5, O-O, e8=Q) or UCI notation (g1f3).
+Commands:
+  undo          take back your last move and the engine's answer
+  flip          turn the board around
+  save <file>   write the game so far as PGN
+  resign        give up the game
+  help          show this text
+  quit          leave without finishing the game";
+
+/// How a game of [`Play`] is set up.
+#[derive(Debug, Clone)]
+pub struct PlaySettings {
+    pub start: Position,
+    /// Colour of the human player
+    pub human: PieceColor,
+    /// Starting time and increment for both players, `None` for an untimed game
+    pub clock: Option<(Duration, Duration)>,
+    /// Time the engine thinks per move in an untimed game
+    pub move_time: Duration,
+    /// File the finished game is saved to
+    pub pgn: Option<String>,
+    /// How the board is drawn, it starts with the human player at the bottom
+    pub renderer: Renderer,
+}
+
+impl Default for PlaySettings {
+    fn default() -> Self {
+        Self {
+            start: Position::default(),
+            human: PieceColor::White,
+            clock: None,
+            move_time: Duration::from_secs(2),
+            pgn: None,
+            renderer: Renderer {
+                colors: true,
+                ..Renderer::default()
+            },
+        }
+    }
+}
+
+/// Interactive game against the engine in the terminal.
+pub struct Play {
+    settings: PlaySettings,
+    position: Position,
+    engine: Engine,
+    renderer: Renderer,
+    /// Time left for White and Black in a timed game
+    remaining: [Duration; 2],
+    // When the side to move started thinking
+    turn_start: Instant,
+    // Hash of every position of the game, to spot repetitions
+    hashes: Vec<u64>,
+    result: Option<(&'static str, String)>,
+}
+
+impl Play {
+    pub fn new(mut settings: PlaySettings) -> Self {
+        // Moves that led to the start are part of the setup, not of this game
+        settings.start.setup_moves = settings.start.log.len();
+        let position = settings.start.clone();
+        let base = settings.clock.map_or(Duration::ZERO, |(base, _)| base);
+        Self {
+            renderer: Renderer {
+                orientation: settings.human,
+                ..settings.renderer.clone()
+            },
+            hashes: vec![position.board.hash(&position.side, &position.log)],
+            remaining: [base, base],
+            turn_start: Instant::now(),
+            position,
+            engine: Engine::default(),
+            settings,
+            result: None,
+        }
+    }
+
+    pub fn run(&mut self) {
+        println!("{}\n", HELP);
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        while self.result.is_none() {
+            self.draw();
+            if let Some(result) = self.game_over() {
+                self.result = Some(result);
+                break;
+            }
+            if self.position.side != self.settings.human {
+                self.engine_move();
+                continue;
+            }
+
+            print!(
+                "{} to move{}> ",
+                color_name(self.position.side),
+                self.clock_text()
+            );
+            let _ = io::stdout().flush();
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                _ => return,
+            };
+            if self.flag_fell() {
+                continue;
+            }
+            if !self.command(line.trim()) {
+                return;
+            }
+        }
+
+        let (result, reason) = self.result.clone().unwrap_or(("*", String::new()));
+        println!("{} {}", result, reason);
+        match self.settings.pgn.clone() {
+            Some(path) => self.save(&path),
+            None => {
+                print!("Save the game as PGN? File name, or enter to skip: ");
+                let _ = io::stdout().flush();
+                if let Some(Ok(path)) = lines.next() {
+                    if !path.trim().is_empty() {
+                        self.save(path.trim());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles the human player's input, returns false to leave the game.
+    fn command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => (),
+            Some("help") => println!("{}", HELP),
+            Some("quit") => return false,
+            Some("flip") => self.renderer.orientation = self.renderer.orientation.opposite(),
+            Some("undo") => self.take_back(),
+            Some("save") => match words.next() {
+                Some(path) => self.save(path),
+                None => println!("save needs a file name"),
+            },
+            Some("resign") => {
+                let winner = self.settings.human.opposite();
+                self.result = Some((
+                    win(winner),
+                    format!("{{{} resigns}}", color_name(self.settings.human)),
+                ));
+            }
+            Some(text) => match self.position.parse_move(text) {
+                Ok(mv) => {
+                    if !self.flag_fell() {
+                        self.charge_clock();
+                        self.play(&mv);
+                    }
+                }
+                Err(_) => println!("Illegal move {}, type help for the commands", text),
+            },
+        }
+        true
+    }
+
+    fn engine_move(&mut self) {
+        let limits = match self.settings.clock {
+            Some((_, increment)) => Limits {
+                clock: TimeControl {
+                    white_time: Some(self.remaining[0]),
+                    black_time: Some(self.remaining[1]),
+                    white_increment: increment,
+                    black_increment: increment,
+                    moves_to_go: None,
+                },
+                ..Limits::default()
+            },
+            None => Limits {
+                move_time: Some(self.settings.move_time),
+                ..Limits::default()
+            },
+        };
+        println!("Thinking...");
+        let result = self.engine.search(&mut self.position, limits, &());
+        if self.flag_fell() {
+            return;
+        }
+        self.charge_clock();
+        if let Some(mv) = result.best_move {
+            println!("YAUCHE plays {}", self.position.to_san(&mv));
+            self.play(&mv);
+        }
+    }
+
+    fn play(&mut self, mv: &Move) {
+        self.position.make_move(mv);
+        self.turn_start = Instant::now();
+        self.hashes.push(
+            self.position
+                .board
+                .hash(&self.position.side, &self.position.log),
+        );
+    }
+
+    /// Takes back the human's last move together with the engine's answer.
+    fn take_back(&mut self) {
+        let played = self.position.log.len() - self.position.setup_moves;
+        let plies = if self.position.side == self.settings.human {
+            2
+        } else {
+            1
+        };
+        if played < plies {
+            println!("Nothing to take back");
+            return;
+        }
+        for _ in 0..plies {
+            if self.position.undo_move().is_ok() {
+                self.hashes.pop();
+            }
+        }
+        self.turn_start = Instant::now();
+    }
+
+    /// Ends the game if the side to move has used up its time.
+    fn flag_fell(&mut self) -> bool {
+        let side = self.position.side;
+        let remaining = &mut self.remaining[side.list_index()];
+        if self.settings.clock.is_none() || self.turn_start.elapsed() <= *remaining {
+            return false;
+        }
+        *remaining = Duration::ZERO;
+        let reason = format!("{{{} forfeits on time}}", color_name(side));
+        self.result = Some((win(side.opposite()), reason));
+        true
+    }
+
+    /// Takes the time of this turn off the clock of the side to move and adds
+    /// the increment.
+    fn charge_clock(&mut self) {
+        if let Some((_, increment)) = self.settings.clock {
+            let remaining = &mut self.remaining[self.position.side.list_index()];
+            *remaining = remaining.saturating_sub(self.turn_start.elapsed()) + increment;
+        }
+    }
+
+    fn clock_text(&self) -> String {
+        if self.settings.clock.is_none() {
+            return String::new();
+        }
+        let mut remaining = self.remaining;
+        let side = self.position.side.list_index();
+        remaining[side] = remaining[side].saturating_sub(self.turn_start.elapsed());
+        let [white, black] =
+            remaining.map(|t| format!("{}:{:02}", t.as_secs() / 60, t.as_secs() % 60));
+        format!(" (White {}, Black {})", white, black)
+    }
+
+    /// Result and reason if the game has ended on the board.
+    fn game_over(&self) -> Option<(&'static str, String)> {
+        let position = &self.position;
+        if position.legal_moves().is_empty() {
+            return Some(if position.board.in_check(&position.side) {
+                let winner = position.side.opposite();
+                (win(winner), format!("{{{} mates}}", color_name(winner)))
+            } else {
+                ("1/2-1/2", "{Stalemate}".to_string())
+            });
+        }
+        if position.halfmove_clock >= 100 {
+            return Some(("1/2-1/2", "{Fifty-move rule}".to_string()));
+        }
+        let current = self.hashes.last()?;
+        if self.hashes.iter().filter(|h| *h == current).count() >= 3 {
+            return Some(("1/2-1/2", "{Threefold repetition}".to_string()));
+        }
+        if insufficient_material(position) {
+            return Some(("1/2-1/2", "{Insufficient material}".to_string()));
+        }
+        None
+    }
+
+    fn save(&self, path: &str) {
+        let mut pgn = Pgn::new(self.settings.start.clone());
+        let (white, black) = match self.settings.human {
+            PieceColor::White => ("Player", "YAUCHE"),
+            PieceColor::Black => ("YAUCHE", "Player"),
+        };
+        pgn.set_tag("Event", "Casual game");
+        pgn.set_tag("White", white);
+        pgn.set_tag("Black", black);
+        pgn.set_tag("Result", self.result.as_ref().map_or("*", |(r, _)| r));
+        if let Some((base, increment)) = self.settings.clock {
+            pgn.set_tag(
+                "TimeControl",
+                &format!("{}+{}", base.as_secs(), increment.as_secs()),
+            );
+        }
+        pgn.moves = self.position.log[self.position.setup_moves..].to_vec();
+        match fs::write(path, pgn.to_string()) {
+            Ok(()) => println!("Saved the game to {}", path),
+            Err(error) => println!("Could not save the game to {}: {}", path, error),
+        }
+    }
+
+    /// Prints the board with the squares of the last move highlighted.
+    fn draw(&mut self) {
+        let last = self.position.log[self.position.setup_moves..].last();
+        self.renderer.highlights = last.map_or(Vec::new(), |m| vec![m.source, m.target]);
+        println!("\n{}", self.renderer.render(&self.position.board));
+    }
+}
+
+fn color_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
+
+fn win(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "1-0",
+        PieceColor::Black => "0-1",
+    }
+}
+
+/// Only kings left, or kings and a single knight or bishop.
+pub(crate) fn insufficient_material(position: &Position) -> bool {
+    let mut minors = 0;
+    for piece in position.board.fields().iter().flatten() {
+        match piece.piece_type {
+            PieceType::King(_) => (),
+            PieceType::Knight | PieceType::Bishop => minors += 1,
+            _ => return false,
+        }
+    }
+    minors <= 1
+}
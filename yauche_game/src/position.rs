@@ -0,0 +1,109 @@
+use crate::board::{Board, PieceColor, PieceType};
+use crate::error::Error;
+use crate::movelist::MoveList;
+use crate::moves::{Move, UndoInfo};
+
+/// A board together with the moves that led to it and the side to move,
+/// everything needed to continue a game from here.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub board: Board,
+    pub log: Vec<Move>,
+    pub side: PieceColor,
+    /// Moves at the start of `log` that only set the position up, see
+    /// [`Position::from_fen`]. They can't be taken back.
+    pub setup_moves: usize,
+    /// Plies since the last capture or pawn move, for the fifty-move rule
+    pub halfmove_clock: u32,
+    /// Starts at 1 and goes up after every Black move
+    pub fullmove_number: u32,
+    // What each move played destroyed, to take it back exactly
+    history: Vec<UndoInfo>,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new(Board::default(), PieceColor::White)
+    }
+}
+
+impl Position {
+    /// `board` with `side` to move and no moves played yet.
+    pub fn new(board: Board, side: PieceColor) -> Self {
+        Self {
+            board,
+            log: Vec::new(),
+            side,
+            setup_moves: 0,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            history: Vec::new(),
+        }
+    }
+
+    /// Finds the legal move written as `text` in long algebraic notation.
+    pub fn find_move(&self, text: &str) -> Option<Move> {
+        self.board.find_move(text, &self.side, &self.log)
+    }
+
+    /// Legal moves of the side to move.
+    pub fn legal_moves(&self) -> MoveList {
+        self.board.get_v_moves(&self.side, &self.log)
+    }
+
+    pub fn make_move(&mut self, mv: &Move) {
+        let pawn_move = matches!(
+            self.board.fields()[mv.source].map(|p| p.piece_type),
+            Some(PieceType::Pawn(_))
+        );
+        let halfmove_clock = self.halfmove_clock;
+        self.halfmove_clock = if pawn_move || mv.captured.is_some() {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        if self.side == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        let undo = self.board.make_move(mv, &mut self.log);
+        self.history.push(UndoInfo {
+            halfmove_clock,
+            ..undo
+        });
+        self.side = self.side.opposite();
+    }
+
+    /// Plays `mv` if it is legal for the side to move, see [`Board::check_move`].
+    /// On error the position is unchanged.
+    pub fn try_make_move(&mut self, mv: &Move) -> Result<Move, Error> {
+        let mv = self.board.check_move(mv, &self.side, &self.log)?;
+        self.make_move(&mv);
+        Ok(mv)
+    }
+
+    /// Takes back the last move played and returns it. The setup moves of a FEN
+    /// position can't be taken back.
+    ///
+    /// ```
+    /// use yauche_game::{Error, Position};
+    ///
+    /// let mut position = Position::default();
+    /// let d4 = position.parse_move("d4").unwrap();
+    /// position.make_move(&d4);
+    /// assert_eq!(position.undo_move(), Ok(d4));
+    /// assert_eq!(position.undo_move(), Err(Error::NoMoveToUndo));
+    /// ```
+    pub fn undo_move(&mut self) -> Result<Move, Error> {
+        if self.log.len() <= self.setup_moves {
+            return Err(Error::NoMoveToUndo);
+        }
+        let undo = self.history.pop().ok_or(Error::NoMoveToUndo)?;
+        self.board.undo_move(&undo, &mut self.log);
+        self.side = self.side.opposite();
+        if self.side == PieceColor::Black {
+            self.fullmove_number -= 1;
+        }
+        self.halfmove_clock = undo.halfmove_clock;
+        Ok(undo.mv)
+    }
+}
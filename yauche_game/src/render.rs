@@ -0,0 +1,122 @@
+use crate::board::{Board, PieceColor};
+
+const RESET: &str = "\x1b[0m";
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+const LIGHT_HIGHLIGHT: &str = "\x1b[48;5;186m";
+const DARK_HIGHLIGHT: &str = "\x1b[48;5;143m";
+const WHITE_PIECE: &str = "\x1b[1;97m";
+const BLACK_PIECE: &str = "\x1b[1;30m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyphs {
+    /// FEN letters, uppercase for White
+    Ascii,
+    /// Chess figurines
+    Unicode,
+}
+
+/// Draws the 8x8 playing area of a [`Board`] as text.
+///
+/// ```
+/// use yauche_game::render::{Glyphs, Renderer};
+/// use yauche_game::{Board, PieceColor};
+///
+/// let renderer = Renderer {
+///     glyphs: Glyphs::Ascii,
+///     orientation: PieceColor::Black,
+///     ..Renderer::default()
+/// };
+/// let text = renderer.render(&Board::default());
+/// assert!(text.starts_with("1  R  N  B  K  Q  B  N  R"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub glyphs: Glyphs,
+    /// Light and dark squares as ANSI background colours
+    pub colors: bool,
+    /// Side at the bottom
+    pub orientation: PieceColor,
+    /// Rank numbers on the left and file letters below
+    pub coordinates: bool,
+    /// Mailbox squares to mark, e.g. the last move. Coloured boards tint them,
+    /// plain ones put the square in brackets.
+    pub highlights: Vec<usize>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            glyphs: Glyphs::Unicode,
+            colors: false,
+            orientation: PieceColor::White,
+            coordinates: true,
+            highlights: Vec::new(),
+        }
+    }
+}
+
+impl Renderer {
+    /// The board as lines of text, each ended by a newline.
+    pub fn render(&self, board: &Board) -> String {
+        let mut ranks: Vec<usize> = (1..=8).rev().collect();
+        let mut files: Vec<usize> = (1..=8).collect();
+        if self.orientation == PieceColor::Black {
+            ranks.reverse();
+            files.reverse();
+        }
+
+        let mut text = String::new();
+        for &rank in &ranks {
+            if self.coordinates {
+                text.push_str(&format!("{} ", rank));
+            }
+            for &file in &files {
+                let square = (rank + 1) * 10 + file;
+                text.push_str(&self.square(board, square, (rank + file) % 2 == 1));
+            }
+            text.push('\n');
+        }
+        if self.coordinates {
+            text.push_str("  ");
+            for &file in &files {
+                text.push_str(&format!(" {} ", (b'a' + file as u8 - 1) as char));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn square(&self, board: &Board, square: usize, light: bool) -> String {
+        let highlighted = self.highlights.contains(&square);
+        let piece = board.fields()[square];
+        let glyph = match (piece, self.glyphs) {
+            (None, _) if self.colors => ' ',
+            (None, _) => '.',
+            (Some(piece), Glyphs::Ascii) => piece.get_letter(),
+            // The filled figurines read better on coloured squares,
+            // the colour of the piece is set by the foreground instead
+            (Some(piece), Glyphs::Unicode) if self.colors => piece.get_filled_symbol(),
+            (Some(piece), Glyphs::Unicode) => piece.get_symbol(),
+        };
+
+        if !self.colors {
+            return if highlighted {
+                format!("[{}]", glyph)
+            } else {
+                format!(" {} ", glyph)
+            };
+        }
+        let background = match (light, highlighted) {
+            (true, false) => LIGHT_SQUARE,
+            (false, false) => DARK_SQUARE,
+            (true, true) => LIGHT_HIGHLIGHT,
+            (false, true) => DARK_HIGHLIGHT,
+        };
+        let foreground = match piece.map(|p| p.piece_color) {
+            Some(PieceColor::Black) => BLACK_PIECE,
+            _ => WHITE_PIECE,
+        };
+        format!("{}{} {} {}", background, foreground, glyph, RESET)
+    }
+}
@@ -0,0 +1,980 @@
+use crate::board::{Board, PieceColor, SIZE};
+use crate::moves::Move;
+use crate::time::{TimeControl, TimeManager};
+use crate::tt::{Bound, Entry, TranspositionTable};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const MATE: i32 = 30000;
+pub const INFINITY: i32 = 32000;
+pub const MAX_PLY: usize = 64;
+
+// Nodes searched between looks at the shared stop flag and node counter
+const CHECK_INTERVAL: u64 = 1024;
+
+// Bound of the history scores, well below the killer band of move ordering
+const MAX_HISTORY: i32 = 16_384;
+
+/// Switches and tuning knobs for the selective parts of the search, so that each
+/// technique can be measured on its own in self-play.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    pub null_move: bool,
+    /// Base depth reduction of the null-move search, grows by one every 6 plies
+    pub null_move_reduction: i32,
+    pub null_move_min_depth: i32,
+
+    pub lmr: bool,
+    pub lmr_min_depth: i32,
+    /// Number of legal moves searched at full depth before reductions start
+    pub lmr_full_moves: usize,
+
+    pub futility: bool,
+    /// Margin per remaining ply under which quiet moves are skipped
+    pub futility_margin: i32,
+    pub futility_max_depth: i32,
+
+    pub reverse_futility: bool,
+    /// Margin per remaining ply over beta at which a node is cut without searching
+    pub reverse_futility_margin: i32,
+    pub reverse_futility_max_depth: i32,
+
+    pub razoring: bool,
+    /// Margin per remaining ply under alpha at which a node drops into quiescence
+    pub razor_margin: i32,
+    pub razor_max_depth: i32,
+
+    pub check_extension: bool,
+
+    /// Half width of the first aspiration window around the previous score
+    pub aspiration_window: i32,
+    pub aspiration_min_depth: i32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            null_move: true,
+            null_move_reduction: 2,
+            null_move_min_depth: 3,
+            lmr: true,
+            lmr_min_depth: 3,
+            lmr_full_moves: 3,
+            futility: true,
+            futility_margin: 150,
+            futility_max_depth: 3,
+            reverse_futility: true,
+            reverse_futility_margin: 120,
+            reverse_futility_max_depth: 3,
+            razoring: true,
+            razor_margin: 300,
+            razor_max_depth: 2,
+            check_extension: true,
+            aspiration_window: 25,
+            aspiration_min_depth: 4,
+        }
+    }
+}
+
+/// When to stop searching. Without any limit the search runs until it reaches
+/// `MAX_PLY` or is stopped from outside.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub depth: Option<i32>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    /// Stop as soon as a mate in this many moves is found
+    pub mate: Option<u32>,
+    /// Ignore the clock and run until stopped
+    pub infinite: bool,
+    /// Search on the opponent's time: run until stopped or until a ponderhit, after
+    /// which the other limits apply with the clock starting at the ponderhit
+    pub ponder: bool,
+    pub clock: TimeControl,
+    /// Number of best lines to search, 0 and 1 both mean only the best one
+    pub multi_pv: usize,
+}
+
+impl Limits {
+    pub fn depth(depth: i32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// Deepest iteration these limits allow.
+    pub fn max_depth(&self) -> i32 {
+        let mut depth = self.depth.unwrap_or(MAX_PLY as i32 - 1);
+        if let Some(moves) = self.mate {
+            // Leave a couple of plies for the pruning to miss the mate at first
+            depth = depth.min(2 * moves as i32 + 1);
+        }
+        depth.clamp(1, MAX_PLY as i32 - 1)
+    }
+}
+
+/// Summary of a finished iteration, see [`crate::SearchObserver::on_iteration`].
+/// With several lines requested it is sent once per line.
+pub struct SearchInfo<'a> {
+    pub depth: i32,
+    /// Rank of the line starting at 1
+    pub multipv: usize,
+    /// Deepest ply reached, quiescence included
+    pub seldepth: usize,
+    pub score: i32,
+    pub nodes: u64,
+    pub nps: u64,
+    /// Permille of the transposition table in use
+    pub hashfull: usize,
+    pub time: Duration,
+    pub pv: &'a [Move],
+}
+
+/// One of the lines found by a MultiPV search.
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    pub best_move: Move,
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: i32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+    /// Best lines ranked by score, the first one is the line above
+    pub lines: Vec<PvLine>,
+}
+
+/// State shared by every thread searching the same position.
+#[derive(Clone)]
+pub struct Shared {
+    pub tt: Arc<TranspositionTable>,
+    pub stop: Arc<AtomicBool>,
+    /// Raised when the opponent played the move a pondering search expected
+    pub ponderhit: Arc<AtomicBool>,
+    /// Nodes of all threads together, updated every `CHECK_INTERVAL` nodes
+    pub nodes: Arc<AtomicU64>,
+}
+
+impl Shared {
+    pub fn new(hash_megabytes: usize) -> Self {
+        Self {
+            tt: Arc::new(TranspositionTable::new(hash_megabytes)),
+            stop: Arc::new(AtomicBool::new(false)),
+            ponderhit: Arc::new(AtomicBool::new(false)),
+            nodes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+pub struct Search {
+    pub options: SearchOptions,
+    /// Thread index, helpers with odd ids search one ply deeper to spread the work
+    pub id: usize,
+    pub nodes: u64,
+    shared: Shared,
+    stopped: bool,
+    pondering: bool,
+    // Whether an iteration is complete, limits only cut the search short after that
+    has_move: bool,
+    time: Option<TimeManager>,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    seldepth: usize,
+    // Triangular PV table: row `ply` holds the best line found from that ply on
+    pv: Vec<[Move; MAX_PLY]>,
+    pv_length: [usize; MAX_PLY],
+    previous_pv: Vec<Move>,
+    // Root moves already taken by better lines of this iteration
+    excluded: Vec<Move>,
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    history: Vec<[i32; SIZE]>,
+    reductions: [[i32; 64]; 64],
+}
+
+impl Search {
+    pub fn new(options: SearchOptions, shared: Shared, id: usize) -> Self {
+        let mut reductions = [[0; 64]; 64];
+        for (depth, row) in reductions.iter_mut().enumerate().skip(1) {
+            for (played, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction = (0.75 + (depth as f64).ln() * (played as f64).ln() / 2.25) as i32;
+            }
+        }
+        Self {
+            options,
+            id,
+            nodes: 0,
+            shared,
+            stopped: false,
+            pondering: false,
+            has_move: false,
+            time: None,
+            deadline: None,
+            node_limit: None,
+            seldepth: 0,
+            pv: vec![[Move::null(); MAX_PLY]; MAX_PLY],
+            pv_length: [0; MAX_PLY],
+            previous_pv: Vec::new(),
+            excluded: Vec::new(),
+            killers: [[None; 2]; MAX_PLY],
+            history: vec![[0; SIZE]; SIZE],
+            reductions,
+        }
+    }
+
+    /// Iterative deepening search for `color` until `limits` are reached, the shared
+    /// stop flag is raised or `time` runs out. `on_iteration` is called after every
+    /// completed depth and line with its principal variation. Returns the result of the
+    /// last completed iteration, with scores from the mover's point of view.
+    pub fn search(
+        &mut self,
+        board: &Board,
+        log: &[Move],
+        color: &PieceColor,
+        limits: &Limits,
+        time: Option<TimeManager>,
+        on_iteration: &mut dyn FnMut(&SearchInfo),
+    ) -> SearchResult {
+        let start = Instant::now();
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.stopped = false;
+        self.pondering = limits.ponder;
+        self.has_move = false;
+        self.time = time;
+        self.deadline = None;
+        self.node_limit = None;
+        self.previous_pv.clear();
+        self.killers = [[None; 2]; MAX_PLY];
+        for row in self.history.iter_mut() {
+            for score in row.iter_mut() {
+                *score /= 2;
+            }
+        }
+
+        let mut log = log.to_vec();
+        let root_moves = board.get_v_moves(color, &log).len();
+        let wanted = limits.multi_pv.clamp(1, root_moves.max(1));
+        let mut result = SearchResult::default();
+        let offset = (self.id % 2) as i32;
+        for d in 1..=limits.max_depth() {
+            let d = (d + offset).min(MAX_PLY as i32 - 1);
+
+            // Every further line is the best one among the moves not taken yet
+            let mut lines: Vec<PvLine> = Vec::new();
+            self.excluded.clear();
+            while lines.len() < wanted {
+                let rank = lines.len();
+                let previous = result.lines.get(rank);
+                self.previous_pv = previous.map(|l| l.pv.clone()).unwrap_or_default();
+                let score =
+                    self.aspiration(board, &mut log, color, d, previous.map_or(0, |l| l.score));
+                if self.stopped || self.pv_length[0] == 0 {
+                    break;
+                }
+                let pv = self.pv[0][..self.pv_length[0]].to_vec();
+                self.excluded.push(pv[0]);
+
+                let elapsed = start.elapsed();
+                let nodes = self.total_nodes();
+                on_iteration(&SearchInfo {
+                    depth: d,
+                    multipv: rank + 1,
+                    seldepth: self.seldepth,
+                    score,
+                    nodes,
+                    nps: (nodes as u128 * 1000 / elapsed.as_millis().max(1)) as u64,
+                    hashfull: self.shared.tt.hashfull(),
+                    time: elapsed,
+                    pv: &pv,
+                });
+                lines.push(PvLine {
+                    best_move: pv[0],
+                    score,
+                    pv,
+                });
+            }
+            if self.stopped {
+                break;
+            }
+            self.excluded.clear();
+            if lines.is_empty() {
+                // Nothing to play at the root, deeper won't change that
+                break;
+            }
+
+            lines.sort_by_key(|l| -l.score);
+            let nodes = self.total_nodes();
+            let score = lines[0].score;
+            result = SearchResult {
+                best_move: Some(lines[0].best_move),
+                score,
+                depth: d,
+                nodes,
+                pv: lines[0].pv.clone(),
+                lines,
+            };
+
+            if limits
+                .mate
+                .is_some_and(|moves| score >= MATE - (2 * moves as i32 - 1))
+            {
+                break;
+            }
+            // Only cut iterations short once there is a move to fall back on
+            self.has_move = true;
+            self.node_limit = limits.nodes;
+            self.check_ponderhit();
+            if self.pondering {
+                continue;
+            }
+            if let Some(time) = self.time.as_mut() {
+                self.deadline = Some(time.hard_deadline());
+                if !time.keep_searching(result.best_move, score) {
+                    break;
+                }
+            }
+            if self.node_limit.is_some_and(|limit| nodes >= limit) {
+                break;
+            }
+        }
+
+        // A pondering search must not answer before the opponent has moved, nor
+        // an infinite one before it is told to stop
+        while (self.pondering || limits.infinite) && !self.shared.stop.load(Ordering::Relaxed) {
+            self.check_ponderhit();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Stopped before the first iteration was done, any legal move beats none
+        if result.best_move.is_none() {
+            result.best_move = board.get_v_moves(color, &log).first().copied();
+        }
+        result
+    }
+
+    fn total_nodes(&self) -> u64 {
+        self.shared.nodes.load(Ordering::Relaxed) + self.nodes % CHECK_INTERVAL
+    }
+
+    /// Searches the root with a narrow window around the previous iteration's score,
+    /// widening it on the failing side until the score falls inside.
+    fn aspiration(
+        &mut self,
+        board: &Board,
+        log: &mut Vec<Move>,
+        color: &PieceColor,
+        depth: i32,
+        previous: i32,
+    ) -> i32 {
+        let mut delta = self.options.aspiration_window;
+        let (mut alpha, mut beta) = if depth >= self.options.aspiration_min_depth {
+            (previous - delta, previous + delta)
+        } else {
+            (-INFINITY, INFINITY)
+        };
+        loop {
+            let score = self.alpha_beta(board, log, color, depth, 0, alpha, beta, false);
+            if self.stopped {
+                return score;
+            }
+            if score <= alpha && alpha > -INFINITY {
+                alpha = (alpha - delta).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                beta = (beta + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+            delta *= 2;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta(
+        &mut self,
+        board: &Board,
+        log: &mut Vec<Move>,
+        color: &PieceColor,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        null_allowed: bool,
+    ) -> i32 {
+        self.pv_length[ply] = ply;
+        if depth <= 0 {
+            return self.quiescence(board, log, color, ply, alpha, beta);
+        }
+        if self.count_node(ply) {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return board.evaluate(color);
+        }
+
+        let options = self.options;
+        let in_check = board.in_check(color);
+        let pv_node = beta - alpha > 1;
+
+        let hash = board.hash(color, log);
+        let entry = self.shared.tt.probe(hash);
+        if let Some(entry) = entry {
+            let score = score_from_tt(entry.score, ply);
+            if !pv_node
+                && entry.depth >= depth
+                && match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                }
+            {
+                return score;
+            }
+        }
+        let static_eval = board.evaluate(color);
+        // Any evaluation beats being mated, so it can't prove a fail high over
+        // a mated score
+        let mated_beta = beta <= -MATE + MAX_PLY as i32;
+
+        if !in_check && !pv_node && !mated_beta {
+            if options.reverse_futility
+                && depth <= options.reverse_futility_max_depth
+                && static_eval - options.reverse_futility_margin * depth >= beta
+            {
+                return static_eval;
+            }
+
+            if options.razoring
+                && depth <= options.razor_max_depth
+                && static_eval + options.razor_margin * depth < alpha
+            {
+                let score = self.quiescence(board, log, color, ply, alpha, beta);
+                if score < alpha {
+                    return score;
+                }
+            }
+
+            // Passing is only a safe bound if the side to move has pieces to shuffle,
+            // pawn-only endings are where zugzwang lives
+            if options.null_move
+                && null_allowed
+                && depth >= options.null_move_min_depth
+                && static_eval >= beta
+                && board.non_pawn_material(color) > 0
+            {
+                let reduction = options.null_move_reduction + depth / 6;
+                log.push(Move::null());
+                let score = -self.alpha_beta(
+                    board,
+                    log,
+                    &color.opposite(),
+                    depth - 1 - reduction,
+                    ply + 1,
+                    -beta,
+                    -beta + 1,
+                    false,
+                );
+                log.pop();
+                if score >= beta {
+                    return if score >= MATE - MAX_PLY as i32 {
+                        beta
+                    } else {
+                        score
+                    };
+                }
+            }
+        }
+
+        let futile = options.futility
+            && !in_check
+            && !pv_node
+            && depth <= options.futility_max_depth
+            && static_eval + options.futility_margin * depth <= alpha;
+
+        let mut moves = board.get_v_moves(color, log);
+        self.order_moves(board, &mut moves, ply, entry);
+
+        let original_alpha = alpha;
+        let mut legal = 0;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        for mv in moves {
+            if ply == 0 && self.excluded.contains(&mv) {
+                continue;
+            }
+            let mut child = *board;
+            child.apply_move(&mv, log);
+            legal += 1;
+
+            let quiet = mv.captured.is_none() && mv.promotion.is_none();
+            let gives_check = child.in_check(&color.opposite());
+            if futile && legal > 1 && quiet && !gives_check {
+                log.pop();
+                continue;
+            }
+
+            // Checks are extended by the side giving them, so that one on the
+            // horizon is still searched: quiescence can't tell mate
+            let new_depth = depth - 1 + i32::from(gives_check && options.check_extension);
+            let reduction = if options.lmr
+                && depth >= options.lmr_min_depth
+                && legal > options.lmr_full_moves
+                && quiet
+                && !in_check
+                && !gives_check
+            {
+                self.reductions[(depth as usize).min(63)][legal.min(63)].min(depth - 2)
+            } else {
+                0
+            };
+
+            // Principal variation search: only the first move gets the full window,
+            // the rest just have to prove they are no better
+            let opponent = color.opposite();
+            let score = if legal == 1 {
+                -self.alpha_beta(
+                    &child,
+                    log,
+                    &opponent,
+                    new_depth,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    true,
+                )
+            } else {
+                let mut score = -self.alpha_beta(
+                    &child,
+                    log,
+                    &opponent,
+                    new_depth - reduction,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    true,
+                );
+                if score > alpha && reduction > 0 {
+                    score = -self.alpha_beta(
+                        &child,
+                        log,
+                        &opponent,
+                        new_depth,
+                        ply + 1,
+                        -alpha - 1,
+                        -alpha,
+                        true,
+                    );
+                }
+                if score > alpha && score < beta {
+                    score = -self.alpha_beta(
+                        &child,
+                        log,
+                        &opponent,
+                        new_depth,
+                        ply + 1,
+                        -beta,
+                        -alpha,
+                        true,
+                    );
+                }
+                score
+            };
+            log.pop();
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(mv, ply);
+                if score >= beta {
+                    if quiet {
+                        self.store_killer(mv, ply);
+                        add_history(&mut self.history[mv.source][mv.target], depth * depth);
+                    }
+                    break;
+                }
+            }
+        }
+
+        if legal == 0 {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // A root searched without some of its moves doesn't have its real score
+        if ply > 0 || self.excluded.is_empty() {
+            self.shared
+                .tt
+                .store(hash, best_move, depth, score_to_tt(best, ply), bound);
+        }
+        best
+    }
+
+    fn quiescence(
+        &mut self,
+        board: &Board,
+        log: &mut Vec<Move>,
+        color: &PieceColor,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.pv_length[ply] = ply;
+        if self.count_node(ply) {
+            return 0;
+        }
+        let stand_pat = board.evaluate(color);
+        if stand_pat >= beta || ply >= MAX_PLY - 1 {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves = board.get_pv_moves(color, log);
+        moves.retain(|m| m.captured.is_some() || m.promotion.is_some());
+        self.order_moves(board, &mut moves, ply, None);
+
+        for mv in moves {
+            // Losing captures cannot raise alpha over the stand pat score
+            if board.see(&mv) < 0 {
+                continue;
+            }
+            let mut child = *board;
+            child.apply_move(&mv, log);
+            if child.in_check(color) {
+                log.pop();
+                continue;
+            }
+            let score = -self.quiescence(&child, log, &color.opposite(), ply + 1, -beta, -alpha);
+            log.pop();
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    fn order_moves(&self, board: &Board, moves: &mut [Move], ply: usize, entry: Option<Entry>) {
+        let killers = self.killers[ply];
+        moves.sort_by_cached_key(|mv| {
+            let score = if entry.is_some_and(|e| e.is_move(mv)) {
+                4_000_000
+            } else if self.previous_pv.get(ply) == Some(mv) {
+                3_000_000
+            } else if mv.captured.is_some() {
+                let see = board.see(mv);
+                if see >= 0 {
+                    2_000_000 + see
+                } else {
+                    -1_000_000 + see
+                }
+            } else if mv.promotion.is_some() {
+                1_900_000
+            } else if Some(*mv) == killers[0] {
+                1_000_001
+            } else if Some(*mv) == killers[1] {
+                1_000_000
+            } else {
+                self.history[mv.source][mv.target]
+            };
+            -score
+        });
+    }
+
+    /// Ends pondering once the ponderhit flag is raised. The clock starts from here
+    /// and the limits take effect, without losing the iterations already done.
+    fn check_ponderhit(&mut self) {
+        if !self.pondering || !self.shared.ponderhit.load(Ordering::Relaxed) {
+            return;
+        }
+        self.pondering = false;
+        if let Some(time) = self.time.as_mut() {
+            time.ponderhit();
+            if self.has_move {
+                self.deadline = Some(time.hard_deadline());
+            }
+        }
+    }
+
+    /// Counts a node at `ply` and returns whether the search has to stop.
+    fn count_node(&mut self, ply: usize) -> bool {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if !self.pondering
+            && self
+                .node_limit
+                .is_some_and(|limit| self.total_nodes() >= limit)
+        {
+            self.stopped = true;
+        }
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.shared
+                .nodes
+                .fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+            self.check_ponderhit();
+            if self.shared.stop.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                self.stopped = true;
+            }
+        }
+        self.stopped
+    }
+
+    fn update_pv(&mut self, mv: Move, ply: usize) {
+        let child_length = self.pv_length[ply + 1];
+        self.pv[ply][ply] = mv;
+        for i in ply + 1..child_length {
+            self.pv[ply][i] = self.pv[ply + 1][i];
+        }
+        self.pv_length[ply] = child_length.max(ply + 1);
+    }
+
+    fn store_killer(&mut self, mv: Move, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+}
+
+/// Moves to mate for a mate score, negative when the side to move is mated,
+/// or `None` for an ordinary score.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score >= MATE - MAX_PLY as i32 {
+        Some((MATE - score + 1) / 2)
+    } else if score <= -MATE + MAX_PLY as i32 {
+        Some(-(MATE + score) / 2)
+    } else {
+        None
+    }
+}
+
+// Gravity update: the bonus shrinks as the score nears MAX_HISTORY, so scores
+// never pass it however long the search runs
+fn add_history(score: &mut i32, bonus: i32) {
+    let bonus = bonus.min(MAX_HISTORY);
+    *score += bonus - *score * bonus / MAX_HISTORY;
+}
+
+// Mate scores are stored relative to the node rather than the root,
+// so they stay correct when the position is reached at another ply
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    #[test]
+    fn history_stays_bounded() {
+        let mut score = 0;
+        for _ in 0..100_000 {
+            add_history(&mut score, 63 * 63);
+            assert!((0..=MAX_HISTORY).contains(&score));
+        }
+        assert!(score > MAX_HISTORY - 63 * 63);
+    }
+
+    #[test]
+    fn mate_in_counts_moves_for_the_side_to_move() {
+        assert_eq!(mate_in(MATE - 1), Some(1));
+        assert_eq!(mate_in(MATE - 3), Some(2));
+        assert_eq!(mate_in(-MATE + 2), Some(-1));
+        assert_eq!(mate_in(-MATE + 4), Some(-2));
+        assert_eq!(mate_in(250), None);
+        assert_eq!(mate_in(-MATE + MAX_PLY as i32 + 1), None);
+    }
+
+    // Searches `fen` on one thread, with the score of line 1 at every depth
+    fn search(fen: &str, limits: Limits) -> (Position, SearchResult, Vec<(i32, i32)>) {
+        let position = Position::from_fen(fen).unwrap();
+        let mut search = Search::new(SearchOptions::default(), Shared::new(1), 0);
+        let mut scores = Vec::new();
+        let result = search.search(
+            &position.board,
+            &position.log,
+            &position.side,
+            &limits,
+            None,
+            &mut |info| {
+                if info.multipv == 1 {
+                    scores.push((info.depth, info.score));
+                }
+            },
+        );
+        (position, result, scores)
+    }
+
+    #[test]
+    fn mate_in_n_by_depth_2n_plus_1() {
+        let problems = [
+            ("1k6/8/1K6/8/8/8/8/6R1 w - - 0 1", 1, &["Rg8#"][..]),
+            ("k7/8/2K5/8/8/8/8/7R w - - 0 1", 2, &["Kc7", "Kb6"][..]),
+            ("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1", 3, &["Ra6+"][..]),
+        ];
+        for (fen, moves, keys) in problems {
+            let plies = 2 * moves - 1;
+            // The depth a mate limit allows, reductions may miss the mate at first
+            let (position, result, scores) = search(fen, Limits::depth(plies + 2));
+            assert_eq!(result.score, MATE - plies, "{}", fen);
+            assert!(scores.iter().all(|(_, score)| *score <= MATE - plies));
+            let key = position.to_san(&result.best_move.unwrap());
+            assert!(keys.contains(&key.as_str()), "{} {}", fen, key);
+        }
+    }
+
+    #[test]
+    fn checks_on_the_horizon_are_extended() {
+        let (_, result, _) = search("1k6/8/1K6/8/8/8/8/6R1 w - - 0 1", Limits::depth(1));
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn mated_side_sees_the_mate_coming() {
+        // Black can only wait with Kb8, and 2. Rh8# follows
+        let (_, result, _) = search("k7/2K5/8/8/8/8/8/7R b - - 1 1", Limits::depth(2));
+        assert_eq!(result.score, -MATE + 2);
+    }
+
+    #[test]
+    fn mate_limit_stops_once_found() {
+        let limits = Limits {
+            mate: Some(2),
+            ..Limits::default()
+        };
+        let (_, result, _) = search("k7/8/2K5/8/8/8/8/7R w - - 0 1", limits);
+        assert!(result.depth <= 5);
+        assert_eq!(result.score, MATE - 3);
+    }
+
+    #[test]
+    fn no_move_when_mated_or_stalemated() {
+        for fen in [
+            "1k4R1/8/1K6/8/8/8/8/8 b - - 1 1",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        ] {
+            let (_, result, scores) = search(fen, Limits::depth(3));
+            assert_eq!(result.best_move, None, "{}", fen);
+            assert!(scores.is_empty());
+        }
+    }
+
+    fn distinct(moves: impl Iterator<Item = Move>) -> bool {
+        let moves: Vec<Move> = moves.collect();
+        moves
+            .iter()
+            .enumerate()
+            .all(|(i, mv)| !moves[i + 1..].contains(mv))
+    }
+
+    #[test]
+    fn multi_pv_lines_are_distinct_and_ordered() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ];
+        for fen in fens {
+            let limits = Limits {
+                multi_pv: 4,
+                ..Limits::depth(4)
+            };
+            let (position, result, _) = search(fen, limits);
+            assert_eq!(result.lines.len(), 4);
+            assert!(
+                distinct(result.lines.iter().map(|line| line.best_move)),
+                "{}",
+                fen
+            );
+            assert!(result.lines.windows(2).all(|w| w[0].score >= w[1].score));
+            let legal = position.legal_moves();
+            assert!(result
+                .lines
+                .iter()
+                .all(|line| line.pv[0] == line.best_move && legal.contains(&line.best_move)));
+            assert_eq!(result.best_move, Some(result.lines[0].best_move));
+            assert_eq!(result.score, result.lines[0].score);
+        }
+    }
+
+    #[test]
+    fn multi_pv_reports_every_rank_per_depth() {
+        let position = Position::default();
+        let mut search = Search::new(SearchOptions::default(), Shared::new(1), 0);
+        let limits = Limits {
+            multi_pv: 3,
+            ..Limits::depth(3)
+        };
+        let mut reports = Vec::new();
+        search.search(
+            &position.board,
+            &position.log,
+            &position.side,
+            &limits,
+            None,
+            &mut |info| reports.push((info.depth, info.multipv, info.pv[0])),
+        );
+        let ranks: Vec<(i32, usize)> = reports.iter().map(|&(d, rank, _)| (d, rank)).collect();
+        let expected: Vec<(i32, usize)> = (1..=3)
+            .flat_map(|depth| (1..=3).map(move |rank| (depth, rank)))
+            .collect();
+        assert_eq!(ranks, expected);
+        for depth in reports.chunks(3) {
+            assert!(distinct(depth.iter().map(|&(_, _, mv)| mv)));
+        }
+    }
+
+    #[test]
+    fn multi_pv_capped_by_legal_moves() {
+        // Only Ka7, Kb7 and Kb8 are legal
+        let limits = Limits {
+            multi_pv: 5,
+            ..Limits::depth(2)
+        };
+        let (_, result, _) = search("k7/8/8/8/8/8/8/K7 b - - 0 1", limits);
+        assert_eq!(result.lines.len(), 3);
+    }
+}
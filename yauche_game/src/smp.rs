@@ -0,0 +1,130 @@
+use crate::board::{Board, PieceColor};
+use crate::moves::Move;
+use crate::search::{Limits, Search, SearchInfo, SearchOptions, SearchResult, Shared};
+use crate::time::TimeManager;
+use crate::tt::TranspositionTable;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Lazy SMP: every thread searches the same root on its own, with its own killer
+/// and history tables. They only cooperate through the shared transposition table,
+/// so whatever one thread finds is picked up by the others for free. Helpers
+/// only pay off with a core each, on fewer cores they slow the main thread down.
+pub struct Threads {
+    options: SearchOptions,
+    shared: Shared,
+    searchers: Vec<Search>,
+}
+
+impl Threads {
+    pub fn new(threads: usize, hash_megabytes: usize, options: SearchOptions) -> Self {
+        let mut pool = Self {
+            options,
+            shared: Shared::new(hash_megabytes),
+            searchers: Vec::new(),
+        };
+        pool.set_threads(threads);
+        pool
+    }
+
+    pub fn threads(&self) -> usize {
+        self.searchers.len()
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        let threads = threads.max(1);
+        self.searchers.truncate(threads);
+        while self.searchers.len() < threads {
+            let id = self.searchers.len();
+            self.searchers
+                .push(Search::new(self.options, self.shared.clone(), id));
+        }
+    }
+
+    /// Replaces the transposition table, dropping everything stored in it.
+    pub fn set_hash(&mut self, megabytes: usize) {
+        self.shared.tt = Arc::new(TranspositionTable::new(megabytes));
+        let threads = self.threads();
+        self.searchers.clear();
+        self.set_threads(threads);
+    }
+
+    /// Forgets everything learned in previous searches, e.g. for a new game.
+    pub fn clear(&mut self) {
+        self.shared.tt.clear();
+        let threads = self.threads();
+        self.searchers.clear();
+        self.set_threads(threads);
+    }
+
+    /// Flag that stops the running search when raised.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.shared.stop.clone()
+    }
+
+    /// Flag that turns a pondering search into a normal one when raised.
+    pub fn ponderhit_flag(&self) -> Arc<AtomicBool> {
+        self.shared.ponderhit.clone()
+    }
+
+    /// Lowers the stop and ponderhit flags for the next search. Raising them before
+    /// this has no effect, so callers must not signal a search before it started.
+    pub fn reset_signals(&self) {
+        self.shared.stop.store(false, Ordering::Relaxed);
+        self.shared.ponderhit.store(false, Ordering::Relaxed);
+    }
+
+    /// Searches with all threads. The first thread searches until `limits` are met or
+    /// `time` runs out and reports through `on_iteration`, the helpers keep going until
+    /// it is done. A helper that completed a deeper iteration within `limits` with at
+    /// least as good a score is trusted over the first thread.
+    pub fn search(
+        &mut self,
+        board: &Board,
+        log: &[Move],
+        color: &PieceColor,
+        limits: &Limits,
+        time: Option<TimeManager>,
+        on_iteration: &mut dyn FnMut(&SearchInfo),
+    ) -> SearchResult {
+        self.shared.nodes.store(0, Ordering::Relaxed);
+
+        // The first thread owns the clock and the node count, helpers run until it stops
+        let helper_limits = Limits {
+            depth: limits.depth,
+            mate: limits.mate,
+            multi_pv: limits.multi_pv,
+            ..Limits::default()
+        };
+        let shared = &self.shared;
+        let (main, helpers) = self.searchers.split_first_mut().unwrap();
+        thread::scope(|scope| {
+            let handles: Vec<_> = helpers
+                .iter_mut()
+                .map(|helper| {
+                    let limits = &helper_limits;
+                    scope.spawn(move || helper.search(board, log, color, limits, None, &mut |_| {}))
+                })
+                .collect();
+            let mut best = main.search(board, log, color, limits, time, on_iteration);
+            shared.stop.store(true, Ordering::Relaxed);
+            for handle in handles {
+                let result = handle.join().unwrap();
+                if result.best_move.is_some()
+                    && result.depth > best.depth
+                    && result.depth <= limits.max_depth()
+                    && result.score >= best.score
+                {
+                    best = SearchResult {
+                        nodes: best.nodes,
+                        ..result
+                    };
+                }
+            }
+            best
+        })
+    }
+}
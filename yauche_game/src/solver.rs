@@ -0,0 +1,380 @@
+use crate::board::{Board, PieceColor};
+use crate::movelist::MoveList;
+use crate::moves::Move;
+use crate::position::Position;
+
+/// What a chess problem asks for, in moves of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stipulation {
+    /// `#N`: the side to move mates in at most N moves against any defence
+    Mate(usize),
+    /// `h#N`: the side to move helps the other side mate it on its Nth move,
+    /// both sides playing together
+    Helpmate(usize),
+    /// `s#N`: the side to move forces the other side to mate it in at most N
+    /// moves, against any defence
+    Selfmate(usize),
+}
+
+impl Stipulation {
+    /// Moves of the side to move the problem takes.
+    pub fn moves(&self) -> usize {
+        match self {
+            Stipulation::Mate(moves)
+            | Stipulation::Helpmate(moves)
+            | Stipulation::Selfmate(moves) => *moves,
+        }
+    }
+}
+
+impl std::fmt::Display for Stipulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stipulation::Mate(moves) => write!(f, "#{}", moves),
+            Stipulation::Helpmate(moves) => write!(f, "h#{}", moves),
+            Stipulation::Selfmate(moves) => write!(f, "s#{}", moves),
+        }
+    }
+}
+
+/// A move that fulfils the stipulation, with the replies to it.
+///
+/// In a direct mate or selfmate every legal reply is listed, each with the
+/// moves that still fulfil the stipulation after it. In a helpmate only the
+/// replies that lead to mate are, so every path down the tree is a solution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub mv: Move,
+    pub replies: Vec<Reply>,
+}
+
+/// A reply to the move of a [`Solution`] and the moves that answer it.
+/// No answers after the last reply, or when the reply itself ends the problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub mv: Move,
+    pub answers: Vec<Solution>,
+}
+
+impl Solution {
+    /// Replies below this move with more than one answer, each a dual.
+    pub fn duals(&self) -> usize {
+        self.replies
+            .iter()
+            .map(|reply| {
+                usize::from(reply.answers.len() > 1)
+                    + reply.answers.iter().map(Solution::duals).sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Every path down the tree, starting with this move.
+    pub fn lines(&self) -> Vec<Vec<Move>> {
+        if self.replies.is_empty() {
+            return vec![vec![self.mv]];
+        }
+        let mut lines = Vec::new();
+        for reply in &self.replies {
+            if reply.answers.is_empty() {
+                lines.push(vec![self.mv, reply.mv]);
+            }
+            for answer in &reply.answers {
+                for line in answer.lines() {
+                    lines.push([vec![self.mv, reply.mv], line].concat());
+                }
+            }
+        }
+        lines
+    }
+}
+
+/// Exhaustive solver for chess problems, separate from the playing engine: it
+/// looks at every move instead of pruning, so that it can prove there is no
+/// other solution.
+///
+/// More than one key move means the problem is cooked, more than one answer to
+/// a reply is a dual.
+///
+/// ```
+/// use yauche_game::solver::{Solver, Stipulation};
+/// use yauche_game::Position;
+///
+/// let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+/// let solutions = Solver::new(Stipulation::Mate(1)).solve(&position);
+/// assert_eq!(solutions.len(), 1);
+/// assert_eq!(position.to_san(&solutions[0].mv), "Ra8#");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Solver {
+    pub stipulation: Stipulation,
+}
+
+impl Solver {
+    pub fn new(stipulation: Stipulation) -> Self {
+        Self { stipulation }
+    }
+
+    /// All key moves of `position` with the full tree below them, empty when
+    /// the stipulation can't be met.
+    pub fn solve(&self, position: &Position) -> Vec<Solution> {
+        if self.stipulation.moves() == 0 {
+            return Vec::new();
+        }
+        let mut search = Search {
+            board: position.board,
+            log: position.log.clone(),
+        };
+        let side = position.side;
+        match self.stipulation {
+            Stipulation::Mate(moves) => search.mate_tree(moves, &side),
+            Stipulation::Helpmate(moves) => search.help_tree(moves, &side),
+            Stipulation::Selfmate(moves) => search.selfmate_tree(moves, &side),
+        }
+    }
+}
+
+// Plays moves on one board and takes them back again
+struct Search {
+    board: Board,
+    log: Vec<Move>,
+}
+
+impl Search {
+    fn moves(&self, color: &PieceColor) -> MoveList {
+        self.board.get_v_moves(color, &self.log)
+    }
+
+    // Runs `f` with `mv` played, then takes it back
+    fn after<T>(&mut self, mv: &Move, f: impl FnOnce(&mut Self) -> T) -> T {
+        let undo = self.board.make_move(mv, &mut self.log);
+        let result = f(self);
+        self.board.undo_move(&undo, &mut self.log);
+        result
+    }
+
+    fn mated(&self, color: &PieceColor) -> bool {
+        self.board.in_check(color) && self.moves(color).is_empty()
+    }
+
+    // Direct mate: `attacker` to move mates in at most `n`
+    fn forces_mate(&mut self, n: usize, attacker: &PieceColor) -> bool {
+        self.moves(attacker)
+            .into_iter()
+            .any(|mv| self.after(&mv, |s| s.mate_follows(n, attacker)))
+    }
+
+    // The defender to move can't avoid being mated in what is left of `n`,
+    // counting the move just played
+    fn mate_follows(&mut self, n: usize, attacker: &PieceColor) -> bool {
+        let defender = attacker.opposite();
+        let replies = self.moves(&defender);
+        if replies.is_empty() {
+            // Mate, or stalemate which fails
+            return self.board.in_check(&defender);
+        }
+        n > 1
+            && replies
+                .into_iter()
+                .all(|reply| self.after(&reply, |s| s.forces_mate(n - 1, attacker)))
+    }
+
+    fn mate_tree(&mut self, n: usize, attacker: &PieceColor) -> Vec<Solution> {
+        let mut solutions = Vec::new();
+        for mv in self.moves(attacker) {
+            if !self.after(&mv, |s| s.mate_follows(n, attacker)) {
+                continue;
+            }
+            let replies = self.after(&mv, |s| {
+                s.moves(&attacker.opposite())
+                    .into_iter()
+                    .map(|reply| Reply {
+                        mv: reply,
+                        answers: s.after(&reply, |s| s.mate_tree(n - 1, attacker)),
+                    })
+                    .collect()
+            });
+            solutions.push(Solution { mv, replies });
+        }
+        solutions
+    }
+
+    // Selfmate: `attacker` to move forces the defender to mate it in at most `n`
+    fn forces_selfmate(&mut self, n: usize, attacker: &PieceColor) -> bool {
+        self.moves(attacker)
+            .into_iter()
+            .any(|mv| self.after(&mv, |s| s.selfmate_follows(n, attacker)))
+    }
+
+    // Every reply of the defender to move mates, or leaves a selfmate in what
+    // is left of `n`
+    fn selfmate_follows(&mut self, n: usize, attacker: &PieceColor) -> bool {
+        let replies = self.moves(&attacker.opposite());
+        !replies.is_empty()
+            && replies.into_iter().all(|reply| {
+                self.after(&reply, |s| {
+                    s.mated(attacker) || (n > 1 && s.forces_selfmate(n - 1, attacker))
+                })
+            })
+    }
+
+    fn selfmate_tree(&mut self, n: usize, attacker: &PieceColor) -> Vec<Solution> {
+        let mut solutions = Vec::new();
+        for mv in self.moves(attacker) {
+            if !self.after(&mv, |s| s.selfmate_follows(n, attacker)) {
+                continue;
+            }
+            let replies = self.after(&mv, |s| {
+                s.moves(&attacker.opposite())
+                    .into_iter()
+                    .map(|reply| Reply {
+                        mv: reply,
+                        answers: s.after(&reply, |s| {
+                            if s.mated(attacker) {
+                                Vec::new()
+                            } else {
+                                s.selfmate_tree(n - 1, attacker)
+                            }
+                        }),
+                    })
+                    .collect()
+            });
+            solutions.push(Solution { mv, replies });
+        }
+        solutions
+    }
+
+    // Helpmate: `helper` to move and the other side mate it together, the
+    // other side's `n`th move being the mate
+    fn help_tree(&mut self, n: usize, helper: &PieceColor) -> Vec<Solution> {
+        let mut solutions = Vec::new();
+        for mv in self.moves(helper) {
+            let replies: Vec<Reply> = self.after(&mv, |s| {
+                let mut replies = Vec::new();
+                for reply in s.moves(&helper.opposite()) {
+                    s.after(&reply, |s| {
+                        if n == 1 {
+                            if s.mated(helper) {
+                                replies.push(Reply {
+                                    mv: reply,
+                                    answers: Vec::new(),
+                                });
+                            }
+                            return;
+                        }
+                        let answers = s.help_tree(n - 1, helper);
+                        if !answers.is_empty() {
+                            replies.push(Reply { mv: reply, answers });
+                        }
+                    });
+                }
+                replies
+            });
+            if !replies.is_empty() {
+                solutions.push(Solution { mv, replies });
+            }
+        }
+        solutions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(stipulation: Stipulation, fen: &str) -> (Position, Vec<Solution>) {
+        let position = Position::from_fen(fen).unwrap();
+        let solutions = Solver::new(stipulation).solve(&position);
+        (position, solutions)
+    }
+
+    // Every line of every solution in SAN, sorted
+    fn lines(position: &Position, solutions: &[Solution]) -> Vec<String> {
+        let mut lines: Vec<String> = solutions
+            .iter()
+            .flat_map(Solution::lines)
+            .map(|line| {
+                let mut position = position.clone();
+                let mut sans = Vec::new();
+                for mv in line {
+                    sans.push(position.to_san(&mv));
+                    position.make_move(&mv);
+                }
+                sans.join(" ")
+            })
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn mate_in_two_with_refutations() {
+        // Kb6 fails to h2, which blocks the rook, and Rh8+ to Ka7
+        let fen = "k7/8/2K5/8/8/7p/8/7R w - - 0 1";
+        let (position, solutions) = solve(Stipulation::Mate(2), fen);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].duals(), 0);
+        assert_eq!(
+            lines(&position, &solutions),
+            ["Kc7 Ka7 Ra1#", "Kc7 h2 Ra1#"]
+        );
+        assert!(solve(Stipulation::Mate(1), fen).1.is_empty());
+    }
+
+    #[test]
+    fn helpmate_in_two_with_two_solutions() {
+        let (position, solutions) =
+            solve(Stipulation::Helpmate(2), "k7/pp6/3K4/8/8/8/B7/8 b - - 0 1");
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(
+            lines(&position, &solutions),
+            ["b5 Kc7 b4 Bd5#", "b6 Kc7 b5 Bd5#"]
+        );
+    }
+
+    #[test]
+    fn selfmate_in_one() {
+        // Qc7 takes the king's last squares, and every knight move uncovers the rook
+        let (position, solutions) = solve(
+            Stipulation::Selfmate(1),
+            "k7/8/8/2Q5/8/2p5/p1P3PP/rn5K w - - 0 1",
+        );
+        assert_eq!(lines(&position, &solutions), ["Qc7 Na3#", "Qc7 Nd2#"]);
+    }
+
+    #[test]
+    fn selfmate_in_two() {
+        // As above, with a pawn move for each side before Black runs out of moves
+        let fen = "k7/8/8/2Q1p3/8/P1p1P3/p1P3PP/rn5K w - - 0 1";
+        assert!(solve(Stipulation::Selfmate(1), fen).1.is_empty());
+        let (position, solutions) = solve(Stipulation::Selfmate(2), fen);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(
+            lines(&position, &solutions),
+            ["Qc7 Nd2#", "Qc7 Nxa3#", "Qc7 e4 a4 Na3#", "Qc7 e4 a4 Nd2#"]
+        );
+    }
+
+    #[test]
+    fn cooked() {
+        let (position, solutions) = solve(Stipulation::Mate(2), "k7/8/2K5/8/8/8/8/7R w - - 0 1");
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(
+            lines(&position, &solutions),
+            ["Kb6 Kb8 Rh8#", "Kc7 Ka7 Ra1#"]
+        );
+    }
+
+    #[test]
+    fn dual() {
+        let (position, solutions) = solve(Stipulation::Mate(2), "k7/8/2K5/8/8/8/3R4/1R6 w - - 0 1");
+        let key = solutions
+            .iter()
+            .find(|solution| position.to_san(&solution.mv) == "Kc7")
+            .unwrap();
+        assert_eq!(key.duals(), 1);
+        assert_eq!(
+            lines(&position, std::slice::from_ref(key)),
+            ["Kc7 Ka7 Ra1#", "Kc7 Ka7 Ra2#"]
+        );
+    }
+}
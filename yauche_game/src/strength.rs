@@ -0,0 +1,335 @@
+use crate::search::{Limits, PvLine};
+use crate::zobrist::split_mix;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MAX_SKILL_LEVEL: u32 = 20;
+/// Elo assumed for level 0, the origin of the scale
+pub const MIN_ELO: u32 = 800;
+/// Elo gained per skill level, measured in self-play
+const ELO_PER_LEVEL: u32 = 102;
+/// Elo of the strongest limited level, full strength is beyond the scale
+pub const MAX_ELO: u32 = MIN_ELO + (MAX_SKILL_LEVEL - 1) * ELO_PER_LEVEL;
+/// Lines searched to have something to choose from
+const CANDIDATE_LINES: usize = 4;
+
+/// How well the engine plays, from skill level 0 up to full strength at
+/// `MAX_SKILL_LEVEL`.
+///
+/// A weaker engine searches less deep and fewer nodes, then picks among its best
+/// MultiPV lines at random. Every line's score gets a random bonus, bigger for
+/// lower levels and for lines further behind, and the best total is played. This
+/// is the error model of Stockfish's skill levels.
+///
+/// Levels are put on an Elo scale by [`Skill::from_elo`]. The scale comes from
+/// self-play: every level played 16 games against the level below, from eight
+/// openings with both colours, and a straight line through the summed Elo
+/// differences gave `ELO_PER_LEVEL`. Only the differences are measured, level 0
+/// is assumed to play at `MIN_ELO` since no rated opponent was involved. The
+/// ignored `calibrate` test in this module replays the match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    level: u32,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::level(MAX_SKILL_LEVEL)
+    }
+}
+
+impl Skill {
+    pub fn level(level: u32) -> Self {
+        Self {
+            level: level.min(MAX_SKILL_LEVEL),
+        }
+    }
+
+    /// Level closest to playing at `elo`, which is clamped to `MIN_ELO..=MAX_ELO`.
+    pub fn from_elo(elo: u32) -> Self {
+        let above = elo.clamp(MIN_ELO, MAX_ELO) - MIN_ELO;
+        Self::level((above + ELO_PER_LEVEL / 2) / ELO_PER_LEVEL)
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL
+    }
+
+    /// Caps `limits` to what this level may search. Lines beyond the
+    /// `multi_pv` asked for are searched to pick from, [`Engine::search`] keeps
+    /// them out of the reports.
+    ///
+    /// [`Engine::search`]: crate::Engine::search
+    pub fn limit(&self, limits: &mut Limits) {
+        if !self.is_limited() {
+            return;
+        }
+        let depth = 1 + self.level as i32 / 2;
+        let nodes = (200.0 * 1.5f64.powi(self.level as i32)) as u64;
+        limits.depth = Some(limits.depth.map_or(depth, |d| d.min(depth)));
+        limits.nodes = Some(limits.nodes.map_or(nodes, |n| n.min(nodes)));
+        limits.multi_pv = limits.multi_pv.max(CANDIDATE_LINES);
+    }
+
+    /// Index of the line to play among `lines`, sorted best first.
+    pub fn pick(&self, lines: &[PvLine], random: &mut Random) -> usize {
+        if !self.is_limited() || lines.len() < 2 {
+            return 0;
+        }
+        let top = lines[0].score;
+        // Don't let the spread grow beyond a pawn, mate scores would make it huge
+        let spread = (top - lines[lines.len() - 1].score).min(100) as f64;
+        let weakness = 120.0 - 2.0 * self.level as f64;
+        let mut best = (0, i32::MIN);
+        for (i, line) in lines.iter().enumerate() {
+            let push = (weakness * (top - line.score) as f64
+                + spread * (random.next_u64() % weakness as u64) as f64)
+                / 128.0;
+            let total = line.score + push as i32;
+            if total > best.1 {
+                best = (i, total);
+            }
+        }
+        best.0
+    }
+}
+
+/// Small random number generator, seeded from the clock so games differ.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_nanos() as u64);
+        Self::new(seed)
+    }
+}
+
+impl Random {
+    /// Generator giving the same numbers every time for the same `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let (state, value) = split_mix(self.state);
+        self.state = state;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PieceColor;
+    use crate::engine::Engine;
+    use crate::moves::Move;
+    use crate::play::insufficient_material;
+    use crate::position::Position;
+    use std::time::Instant;
+
+    fn lines(scores: &[i32]) -> Vec<PvLine> {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| {
+                let mv = Move::new(31 + i, 41 + i);
+                PvLine {
+                    best_move: mv,
+                    score,
+                    pv: vec![mv],
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_picks() {
+        let lines = lines(&[50, 40, 10, -30]);
+        for level in 0..MAX_SKILL_LEVEL {
+            let skill = Skill::level(level);
+            let mut first = Random::new(7);
+            let mut second = Random::new(7);
+            let picks: Vec<usize> = (0..32).map(|_| skill.pick(&lines, &mut first)).collect();
+            let again: Vec<usize> = (0..32).map(|_| skill.pick(&lines, &mut second)).collect();
+            assert_eq!(picks, again);
+            assert!(picks.iter().all(|&pick| pick < lines.len()));
+        }
+    }
+
+    #[test]
+    fn full_strength_plays_the_best_line() {
+        let lines = lines(&[50, 40, 10, -30]);
+        let mut random = Random::new(7);
+        for _ in 0..32 {
+            assert_eq!(Skill::default().pick(&lines, &mut random), 0);
+        }
+    }
+
+    #[test]
+    fn weakest_level_strays_from_the_best_line() {
+        let lines = lines(&[50, 40, 10, -30]);
+        let mut random = Random::new(7);
+        let picks: Vec<usize> = (0..64)
+            .map(|_| Skill::level(0).pick(&lines, &mut random))
+            .collect();
+        assert!(picks.iter().any(|&pick| pick > 0));
+    }
+
+    #[test]
+    fn limit_searches_extra_lines() {
+        let mut limits = Limits::depth(12);
+        Skill::level(4).limit(&mut limits);
+        assert_eq!(limits.depth, Some(3));
+        assert_eq!(limits.multi_pv, CANDIDATE_LINES);
+        assert!(limits.nodes.is_some());
+
+        let mut limits = Limits::depth(12);
+        Skill::default().limit(&mut limits);
+        assert_eq!(limits.depth, Some(12));
+        assert_eq!(limits.multi_pv, 0);
+    }
+
+    #[test]
+    fn elo_is_clamped_to_the_scale() {
+        assert_eq!(Skill::from_elo(0), Skill::level(0));
+        assert_eq!(Skill::from_elo(MIN_ELO), Skill::level(0));
+        assert_eq!(Skill::from_elo(MAX_ELO), Skill::level(MAX_SKILL_LEVEL - 1));
+        assert_eq!(Skill::from_elo(u32::MAX), Skill::level(MAX_SKILL_LEVEL - 1));
+        assert!(Skill::from_elo(MAX_ELO).is_limited());
+    }
+
+    #[test]
+    fn elo_picks_the_nearest_level() {
+        for level in 0..MAX_SKILL_LEVEL {
+            let elo = MIN_ELO + level * ELO_PER_LEVEL;
+            assert_eq!(Skill::from_elo(elo), Skill::level(level));
+            assert_eq!(
+                Skill::from_elo(elo + ELO_PER_LEVEL / 3),
+                Skill::level(level)
+            );
+            assert_eq!(
+                Skill::from_elo(elo - ELO_PER_LEVEL / 3),
+                Skill::level(level)
+            );
+        }
+    }
+
+    // Short openings, each played with both colours so the games differ
+    const OPENINGS: [&str; 8] = [
+        "e4 e5 Nf3 Nc6",
+        "e4 c5 Nf3 d6",
+        "d4 d5 c4 e6",
+        "d4 Nf6 c4 g6",
+        "e4 e6 d4 d5",
+        "c4 e5 Nc3 Nf6",
+        "e4 c6 d4 d5",
+        "Nf3 d5 g3 Nf6",
+    ];
+
+    // Score of the engine at `first` against `second`, from its point of view:
+    // 1 for a win, 0.5 for a draw. A side whose own score stays below -800
+    // for four moves resigns.
+    fn play_game(first: &mut Engine, second: &mut Engine, opening: &str, first_white: bool) -> f64 {
+        let mut position = Position::default();
+        for san in opening.split_whitespace() {
+            let mv = position.parse_move(san).unwrap();
+            position.make_move(&mv);
+        }
+        first.new_game();
+        second.new_game();
+        let mut hashes = vec![position.board.hash(&position.side, &position.log)];
+        let mut losing = [0; 2];
+        for _ in 0..300 {
+            let white_to_move = position.side == PieceColor::White;
+            let first_to_move = white_to_move == first_white;
+            if position.legal_moves().is_empty() {
+                if !position.board.in_check(&position.side) {
+                    return 0.5;
+                }
+                return if first_to_move { 0.0 } else { 1.0 };
+            }
+            let current = hashes[hashes.len() - 1];
+            if position.halfmove_clock >= 100
+                || hashes.iter().filter(|&&h| h == current).count() >= 3
+                || insufficient_material(&position)
+            {
+                return 0.5;
+            }
+            let engine = if first_to_move {
+                &mut *first
+            } else {
+                &mut *second
+            };
+            let result = engine.search(&mut position, Limits::default(), &());
+            let side = usize::from(!first_to_move);
+            losing[side] = if result.score < -800 {
+                losing[side] + 1
+            } else {
+                0
+            };
+            if losing[side] >= 4 {
+                return if first_to_move { 0.0 } else { 1.0 };
+            }
+            position.make_move(&result.best_move.unwrap());
+            hashes.push(position.board.hash(&position.side, &position.log));
+        }
+        0.5
+    }
+
+    // Elo difference a score fraction stands for, capped at 98%
+    fn elo_difference(score: f64) -> f64 {
+        let score = score.clamp(0.02, 0.98);
+        400.0 * (score / (1.0 - score)).log10()
+    }
+
+    /// Self-play match behind `ELO_PER_LEVEL`: each level plays every opening
+    /// with both colours against the level below. Run with
+    /// `cargo test --release calibrate -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn calibrate() {
+        let rounds: usize = std::env::var("CALIBRATE_ROUNDS")
+            .ok()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(1);
+        let mut elo = 0.0;
+        // Least squares line through level 0 for ELO_PER_LEVEL
+        let (mut sum_le, mut sum_ll) = (0.0, 0.0);
+        println!("level  score  games  gain   elo  seconds");
+        for level in 1..MAX_SKILL_LEVEL {
+            let start = Instant::now();
+            let mut stronger = Engine::default();
+            stronger.skill = Skill::level(level);
+            let mut weaker = Engine::default();
+            weaker.skill = Skill::level(level - 1);
+            let mut score = 0.0;
+            let mut games = 0;
+            for _ in 0..rounds {
+                for opening in OPENINGS {
+                    for first_white in [true, false] {
+                        score += play_game(&mut stronger, &mut weaker, opening, first_white);
+                        games += 1;
+                    }
+                }
+            }
+            let gain = elo_difference(score / games as f64);
+            elo += gain;
+            sum_le += level as f64 * elo;
+            sum_ll += (level * level) as f64;
+            println!(
+                "{:>5} {:>6.1} {:>6} {:>5.0} {:>5.0} {:>8}",
+                level,
+                score,
+                games,
+                gain,
+                elo,
+                start.elapsed().as_secs()
+            );
+        }
+        println!("elo per level {:.0}", sum_le / sum_ll);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::board::{Board, PieceColor};
+use std::fmt::Write;
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const HIGHLIGHT: &str = "#cdd26a";
+const ARROW: &str = "#15781b";
+
+/// Draws a [`Board`] as a standalone SVG image.
+///
+/// Pieces are the filled Unicode figurines, white ones with a dark outline, so
+/// viewers need a font with the chess symbols.
+///
+/// ```
+/// use yauche_game::svg::SvgRenderer;
+/// use yauche_game::Board;
+///
+/// let svg = SvgRenderer {
+///     arrows: vec![(35, 55)],
+///     ..SvgRenderer::default()
+/// }
+/// .render(&Board::default());
+/// assert!(svg.starts_with("<svg"));
+/// assert!(svg.contains("<line"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SvgRenderer {
+    /// Side at the bottom
+    pub orientation: PieceColor,
+    /// Frame with file letters and rank numbers around the board
+    pub coordinates: bool,
+    /// Mailbox squares to tint
+    pub highlights: Vec<usize>,
+    /// Arrows drawn from the first mailbox square to the second. Arrows with a
+    /// square off the board are left out.
+    pub arrows: Vec<(usize, usize)>,
+    /// Width of a square in pixels
+    pub square_size: u32,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self {
+            orientation: PieceColor::White,
+            coordinates: true,
+            highlights: Vec::new(),
+            arrows: Vec::new(),
+            square_size: 45,
+        }
+    }
+}
+
+impl SvgRenderer {
+    pub fn render(&self, board: &Board) -> String {
+        let size = self.square_size as f64;
+        let margin = if self.coordinates { size / 2.0 } else { 0.0 };
+        let total = 8.0 * size + 2.0 * margin;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" \
+             viewBox=\"0 0 {0} {0}\">",
+            total
+        );
+        let _ = writeln!(
+            svg,
+            "<defs><marker id=\"arrowhead\" markerWidth=\"4\" markerHeight=\"4\" refX=\"2\" \
+             refY=\"2\" orient=\"auto\"><path d=\"M0,0 L4,2 L0,4 z\" fill=\"{}\"/></marker></defs>",
+            ARROW
+        );
+        if self.coordinates {
+            let _ = writeln!(
+                svg,
+                "<rect width=\"{0}\" height=\"{0}\" fill=\"#404040\"/>",
+                total
+            );
+        }
+
+        for rank in 1..=8 {
+            for file in 1..=8 {
+                let square = (rank + 1) * 10 + file;
+                let (x, y) = self.corner(square, margin);
+                let color = if self.highlights.contains(&square) {
+                    HIGHLIGHT
+                } else if (rank + file) % 2 == 1 {
+                    LIGHT_SQUARE
+                } else {
+                    DARK_SQUARE
+                };
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>",
+                    x, y, size, color
+                );
+
+                if let Some(piece) = board.fields()[square] {
+                    let (fill, stroke) = match piece.piece_color {
+                        PieceColor::White => ("#ffffff", "#000000"),
+                        PieceColor::Black => ("#000000", "none"),
+                    };
+                    let _ = writeln!(
+                        svg,
+                        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+                         dominant-baseline=\"central\" fill=\"{}\" stroke=\"{}\" \
+                         stroke-width=\"1\">{}</text>",
+                        x + size / 2.0,
+                        y + size / 2.0,
+                        size * 0.8,
+                        fill,
+                        stroke,
+                        piece.get_filled_symbol()
+                    );
+                }
+            }
+        }
+
+        if self.coordinates {
+            for i in 1..=8 {
+                let file = self.corner(20 + i, margin).0 + size / 2.0;
+                let rank = self.corner(10 + i * 10 + 1, margin).1 + size / 2.0;
+                for y in [margin / 2.0, total - margin / 2.0] {
+                    let _ = writeln!(
+                        svg,
+                        "{}",
+                        label(file, y, size, (b'a' + i as u8 - 1) as char)
+                    );
+                }
+                for x in [margin / 2.0, total - margin / 2.0] {
+                    let _ = writeln!(svg, "{}", label(x, rank, size, (b'0' + i as u8) as char));
+                }
+            }
+        }
+
+        let arrows = self
+            .arrows
+            .iter()
+            .filter(|(from, to)| Board::is_playing_square(*from) && Board::is_playing_square(*to));
+        for &(from, to) in arrows {
+            let center = |square: usize| {
+                let (x, y) = self.corner(square, margin);
+                (x + size / 2.0, y + size / 2.0)
+            };
+            let (x1, y1) = center(from);
+            let (x2, y2) = center(to);
+            // Stop short so the arrowhead ends inside the target square
+            let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt().max(1.0);
+            let shorten = size * 0.35;
+            let (x2, y2) = (
+                x2 - (x2 - x1) / length * shorten,
+                y2 - (y2 - y1) / length * shorten,
+            );
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" \
+                 stroke-width=\"{}\" stroke-linecap=\"round\" opacity=\"0.8\" \
+                 marker-end=\"url(#arrowhead)\"/>",
+                x1,
+                y1,
+                x2,
+                y2,
+                ARROW,
+                size / 6.0
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Top left corner of a mailbox square in pixels. `square` must be one of
+    /// the 64 playing squares.
+    fn corner(&self, square: usize, margin: f64) -> (f64, f64) {
+        let file = (square % 10 - 1) as f64;
+        let rank = (square / 10 - 2) as f64;
+        let (column, row) = match self.orientation {
+            PieceColor::White => (file, 7.0 - rank),
+            PieceColor::Black => (7.0 - file, rank),
+        };
+        let size = self.square_size as f64;
+        (margin + column * size, margin + row * size)
+    }
+}
+
+fn label(x: f64, y: f64, size: f64, text: char) -> String {
+    format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" \
+         text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"#e0e0e0\">{}</text>",
+        x,
+        y,
+        size * 0.3,
+        text
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_off_the_board_are_left_out() {
+        let svg = SvgRenderer {
+            arrows: vec![(35, 55), (0, 55), (35, 119), (20, 29)],
+            ..SvgRenderer::default()
+        }
+        .render(&Board::default());
+        assert_eq!(svg.matches("<line").count(), 1);
+    }
+}
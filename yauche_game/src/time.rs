@@ -0,0 +1,245 @@
+use crate::board::PieceColor;
+use crate::moves::Move;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+// Moves we plan to still play when the clock doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+// The soft limit may stretch up to this many times on an unstable search
+const HARD_LIMIT_FACTOR: u32 = 4;
+
+/// Clock state as sent with `go` by UCI or set up by xboard's `level`/`time`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeControl {
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+impl TimeControl {
+    /// Whether there is a clock to manage at all.
+    pub fn is_timed(&self) -> bool {
+        self.white_time.is_some() || self.black_time.is_some()
+    }
+}
+
+/// Decides when to stop an iterative deepening search.
+///
+/// The soft limit is checked between iterations and shrinks while the best move
+/// stays the same, but grows when the score drops. The hard limit is never
+/// exceeded: the search is cut off mid-iteration once it passes. A fixed move
+/// time isn't scaled at all.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+    best_move: Option<Move>,
+    stability: u32,
+    previous_score: Option<i32>,
+    score_drop: i32,
+    // Set for a fixed move time, which isn't scaled
+    fixed: bool,
+}
+
+impl TimeManager {
+    /// Budget for the side `color` playing on `clock`.
+    pub fn new(clock: &TimeControl, color: &PieceColor, overhead: Duration) -> Self {
+        let (left, increment) = match color {
+            PieceColor::White => (clock.white_time, clock.white_increment),
+            PieceColor::Black => (clock.black_time, clock.black_increment),
+        };
+        let available = left.unwrap_or_default().saturating_sub(overhead);
+        let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let hard = (available / moves_to_go + increment) * HARD_LIMIT_FACTOR;
+        let hard = hard.min(available * 3 / 4);
+        let soft = (available / moves_to_go + increment * 3 / 4).min(hard);
+        Self::with_limits(soft, hard)
+    }
+
+    /// Spends exactly `move_time`, less the overhead.
+    pub fn fixed(move_time: Duration, overhead: Duration) -> Self {
+        let limit = move_time.saturating_sub(overhead);
+        Self {
+            fixed: true,
+            ..Self::with_limits(limit, limit)
+        }
+    }
+
+    fn with_limits(soft: Duration, hard: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            soft,
+            hard,
+            best_move: None,
+            stability: 0,
+            previous_score: None,
+            score_drop: 0,
+            fixed: false,
+        }
+    }
+
+    /// Restarts the clock when the opponent played the move the engine pondered on.
+    /// Time spent pondering was the opponent's, so the whole budget is still there.
+    pub fn ponderhit(&mut self) {
+        self.start = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Point in time at which the search has to be aborted.
+    pub fn hard_deadline(&self) -> Instant {
+        self.start + self.hard
+    }
+
+    /// Records a finished iteration and returns whether to start another one.
+    pub fn keep_searching(&mut self, best_move: Option<Move>, score: i32) -> bool {
+        if best_move == self.best_move {
+            self.stability = (self.stability + 1).min(4);
+        } else {
+            self.stability = 0;
+            self.best_move = best_move;
+        }
+        if let Some(previous) = self.previous_score {
+            self.score_drop = (previous - score).max(0);
+        }
+        self.previous_score = Some(score);
+
+        self.elapsed() < self.soft_limit()
+    }
+
+    /// Soft limit scaled for best move stability and the last score drop.
+    pub fn soft_limit(&self) -> Duration {
+        if self.fixed {
+            return self.soft;
+        }
+        // From 140% right after a change of mind down to 72% after 4 stable iterations
+        let stability = 140 - 17 * self.stability;
+        let drop = 100 + self.score_drop.min(100) as u32;
+        (self.soft * stability / 100 * drop / 100).min(self.hard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn clock(time: u64, increment: u64, moves_to_go: Option<u32>) -> TimeControl {
+        TimeControl {
+            white_time: Some(ms(time)),
+            black_time: Some(ms(time / 2)),
+            white_increment: ms(increment),
+            black_increment: ms(increment / 2),
+            moves_to_go,
+        }
+    }
+
+    #[test]
+    fn sudden_death_with_increment() {
+        let time = TimeManager::new(&clock(60_030, 1000, None), &PieceColor::White, ms(30));
+        // 60 s over 30 moves plus the increment, three quarters of it for the soft limit
+        assert_eq!(time.soft, ms(2000 + 750));
+        assert_eq!(time.hard, ms((2000 + 1000) * 4));
+    }
+
+    #[test]
+    fn uses_the_clock_of_the_side_to_move() {
+        let time = TimeManager::new(&clock(60_000, 1000, None), &PieceColor::Black, ms(0));
+        // 30 s and 500 ms increment
+        assert_eq!(time.soft, ms(1000 + 375));
+        assert_eq!(time.hard, ms((1000 + 500) * 4));
+    }
+
+    #[test]
+    fn moves_to_go() {
+        let time = TimeManager::new(&clock(10_000, 0, Some(10)), &PieceColor::White, ms(0));
+        assert_eq!(time.soft, ms(1000));
+        assert_eq!(time.hard, ms(4000));
+    }
+
+    #[test]
+    fn hard_limit_keeps_a_quarter_of_the_clock() {
+        let time = TimeManager::new(&clock(1000, 0, Some(1)), &PieceColor::White, ms(0));
+        assert_eq!(time.hard, ms(750));
+        assert_eq!(time.soft, ms(750));
+    }
+
+    #[test]
+    fn overhead_larger_than_the_clock() {
+        let time = TimeManager::new(&clock(20, 0, None), &PieceColor::White, ms(30));
+        assert_eq!(time.soft, Duration::ZERO);
+        assert_eq!(time.hard, Duration::ZERO);
+    }
+
+    #[test]
+    fn fixed_move_time() {
+        let time = TimeManager::fixed(ms(500), ms(30));
+        assert_eq!(time.soft, ms(470));
+        assert_eq!(time.hard, ms(470));
+    }
+
+    #[test]
+    fn fixed_move_time_isnt_scaled() {
+        let mut time = TimeManager::fixed(ms(1000), ms(0));
+        let mv = Move::new(35, 55);
+        for _ in 0..5 {
+            time.keep_searching(Some(mv), 20);
+        }
+        assert_eq!(time.soft_limit(), ms(1000));
+        time.keep_searching(Some(Move::new(35, 45)), -300);
+        assert_eq!(time.soft_limit(), ms(1000));
+    }
+
+    #[test]
+    fn fixed_move_time_searches_until_it_is_spent() {
+        let mut time = TimeManager::fixed(ms(50), ms(0));
+        let mv = Move::new(35, 55);
+        while time.keep_searching(Some(mv), 20) {}
+        assert!(time.elapsed() >= ms(50));
+    }
+
+    #[test]
+    fn soft_limit_follows_stability_and_score() {
+        let mut time = TimeManager::with_limits(ms(1000), ms(4000));
+        let mv = Move::new(35, 55);
+        // A new best move
+        time.keep_searching(Some(mv), 20);
+        assert_eq!(time.soft_limit(), ms(1400));
+        // Stable for four iterations
+        for _ in 0..4 {
+            time.keep_searching(Some(mv), 20);
+        }
+        assert_eq!(time.soft_limit(), ms(720));
+        // Staying stable doesn't shrink it further
+        time.keep_searching(Some(mv), 20);
+        assert_eq!(time.soft_limit(), ms(720));
+        // Losing half a pawn stretches it by half
+        time.keep_searching(Some(mv), -30);
+        assert_eq!(time.soft_limit(), ms(1080));
+        // Never beyond the hard limit
+        time.keep_searching(Some(Move::new(35, 45)), -300);
+        assert_eq!(time.soft_limit(), ms(2800));
+        let mut time = TimeManager::with_limits(ms(3000), ms(4000));
+        time.keep_searching(Some(mv), 0);
+        time.keep_searching(Some(Move::new(35, 45)), -300);
+        assert_eq!(time.soft_limit(), ms(4000));
+    }
+
+    #[test]
+    fn stops_once_past_the_soft_limit() {
+        let mut time = TimeManager::with_limits(Duration::ZERO, ms(100));
+        assert!(!time.keep_searching(None, 0));
+        let mut time = TimeManager::with_limits(Duration::from_secs(60), Duration::from_secs(60));
+        assert!(time.keep_searching(None, 0));
+        assert!(time.hard_deadline() > Instant::now());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::moves::Move;
+use crate::packed::PackedMove;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub depth: i32,
+    pub score: i32,
+    pub bound: Bound,
+    mv: PackedMove,
+}
+
+impl Entry {
+    /// Whether `mv` is the move stored with this entry.
+    pub fn is_move(&self, mv: &Move) -> bool {
+        self.mv != PackedMove::NULL && PackedMove::from(*mv) == self.mv
+    }
+}
+
+/// Transposition table shared between search threads without locks.
+///
+/// Every slot is a pair of atomics holding the packed data and the key xor-ed with
+/// that data. A slot torn by two threads writing at once no longer decodes to its
+/// key, so it reads as a miss instead of returning another position's data.
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = (megabytes.max(1) << 20) / std::mem::size_of::<[AtomicU64; 2]>();
+        // Round down to a power of two so the index is a mask
+        let size = 1 << (usize::BITS - 1 - wanted.leading_zeros());
+        Self {
+            slots: (0..size)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot[0].store(0, Ordering::Relaxed);
+            slot[1].store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Permille of slots in use, sampled from the start of the table.
+    pub fn hashfull(&self) -> usize {
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|slot| slot[1].load(Ordering::Relaxed) != 0)
+            .count();
+        used * 1000 / sample
+    }
+
+    fn slot(&self, hash: u64) -> &[AtomicU64; 2] {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        let slot = self.slot(hash);
+        let data = slot[1].load(Ordering::Relaxed);
+        if data == 0 || slot[0].load(Ordering::Relaxed) ^ data != hash {
+            return None;
+        }
+        Some(Entry {
+            mv: PackedMove(data as u16),
+            bound: match (data >> 19) & 0x3 {
+                1 => Bound::Exact,
+                2 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+            depth: ((data >> 24) & 0xFF) as i32,
+            score: ((data >> 32) as u16 as i16) as i32,
+        })
+    }
+
+    pub fn store(&self, hash: u64, mv: Option<Move>, depth: i32, score: i32, bound: Bound) {
+        let mv = mv.map_or(PackedMove::NULL, PackedMove::from);
+        let bound = match bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let data = mv.0 as u64
+            | bound << 19
+            | (depth.clamp(0, 255) as u64) << 24
+            | (score as i16 as u16 as u64) << 32;
+        let slot = self.slot(hash);
+        slot[0].store(hash ^ data, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> TranspositionTable {
+        TranspositionTable::new(1)
+    }
+
+    #[test]
+    fn store_then_probe() {
+        let tt = table();
+        let mv = Move::new(35, 55);
+        tt.store(0x1234_5678_9ABC_DEF0, Some(mv), 7, -315, Bound::Lower);
+        let entry = tt.probe(0x1234_5678_9ABC_DEF0).unwrap();
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.score, -315);
+        assert_eq!(entry.bound, Bound::Lower);
+        assert!(entry.is_move(&mv));
+        assert!(!entry.is_move(&Move::new(35, 45)));
+    }
+
+    #[test]
+    fn entry_without_move() {
+        let tt = table();
+        tt.store(42, None, 0, 0, Bound::Upper);
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.bound, Bound::Upper);
+        assert!(!entry.is_move(&Move::null()));
+    }
+
+    #[test]
+    fn misses() {
+        let tt = table();
+        assert!(tt.probe(42).is_none());
+        tt.store(42, None, 3, 10, Bound::Exact);
+        // Same slot, other key
+        let other = 42 | 1 << 60;
+        assert!(tt.probe(other).is_none());
+        tt.clear();
+        assert!(tt.probe(42).is_none());
+    }
+
+    #[test]
+    fn newer_entry_replaces_older() {
+        let tt = table();
+        let other = 42 | 1 << 60;
+        tt.store(42, None, 12, 10, Bound::Exact);
+        tt.store(other, Some(Move::new(22, 43)), 1, -5, Bound::Upper);
+        assert!(tt.probe(42).is_none());
+        let entry = tt.probe(other).unwrap();
+        assert_eq!((entry.depth, entry.score), (1, -5));
+
+        tt.store(other, None, 4, 20, Bound::Lower);
+        let entry = tt.probe(other).unwrap();
+        assert_eq!(
+            (entry.depth, entry.score, entry.bound),
+            (4, 20, Bound::Lower)
+        );
+    }
+
+    #[test]
+    fn torn_slot_reads_as_miss() {
+        let tt = table();
+        tt.store(42, Some(Move::new(35, 55)), 5, 30, Bound::Exact);
+        // Data of another store without its key half
+        tt.slot(42)[1].store(0xFFFF_0000_0000, Ordering::Relaxed);
+        assert!(tt.probe(42).is_none());
+    }
+
+    #[test]
+    fn depth_is_clamped() {
+        let tt = table();
+        tt.store(42, None, 300, 0, Bound::Exact);
+        assert_eq!(tt.probe(42).unwrap().depth, 255);
+        tt.store(42, None, -2, 0, Bound::Exact);
+        assert_eq!(tt.probe(42).unwrap().depth, 0);
+    }
+
+    #[test]
+    fn hashfull_counts_used_slots() {
+        let tt = table();
+        assert_eq!(tt.hashfull(), 0);
+        for hash in 0..500 {
+            tt.store(hash, None, 1, 0, Bound::Exact);
+        }
+        assert_eq!(tt.hashfull(), 500);
+    }
+}
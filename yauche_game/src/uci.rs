@@ -0,0 +1,297 @@
+use crate::engine::{Engine, SearchObserver, StopHandle};
+use crate::position::Position;
+use crate::search::{mate_in, Limits, SearchInfo, MAX_PLY};
+use crate::smp::DEFAULT_HASH_MB;
+use crate::strength::{Skill, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+use crate::time::DEFAULT_MOVE_OVERHEAD;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Depth searched when `go` comes without any limit
+pub const DEFAULT_DEPTH: i32 = 6;
+
+/// Universal Chess Interface front end reading commands from stdin.
+///
+/// Searches run on a background thread so that `stop`, `ponderhit` and
+/// `isready` are answered while the engine thinks.
+pub struct Uci {
+    position: Position,
+    engine: Arc<Mutex<Engine>>,
+    stop: StopHandle,
+    search: Option<JoinHandle<()>>,
+    multi_pv: usize,
+    limit_strength: bool,
+    elo: u32,
+    skill_level: u32,
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        let engine = Engine::default();
+        Self {
+            position: Position::default(),
+            stop: engine.stop_handle(),
+            engine: Arc::new(Mutex::new(engine)),
+            search: None,
+            multi_pv: 1,
+            limit_strength: false,
+            elo: MAX_ELO,
+            skill_level: MAX_SKILL_LEVEL,
+        }
+    }
+}
+
+impl Uci {
+    pub fn run(&mut self) {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if !self.handle(&line) {
+                break;
+            }
+        }
+        self.stop_search();
+    }
+
+    /// Handles one command, returns false once the engine should exit.
+    fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                println!("id name YAUCHE {}", env!("CARGO_PKG_VERSION"));
+                println!("id author MatiF100");
+                println!(
+                    "option name Hash type spin default {} min 1 max 4096",
+                    DEFAULT_HASH_MB
+                );
+                println!("option name Threads type spin default 1 min 1 max 256");
+                println!(
+                    "option name Move Overhead type spin default {} min 0 max 5000",
+                    DEFAULT_MOVE_OVERHEAD.as_millis()
+                );
+                println!("option name MultiPV type spin default 1 min 1 max 256");
+                println!("option name Ponder type check default false");
+                println!(
+                    "option name Skill Level type spin default {0} min 0 max {0}",
+                    MAX_SKILL_LEVEL
+                );
+                println!("option name UCI_LimitStrength type check default false");
+                println!(
+                    "option name UCI_Elo type spin default {1} min {0} max {1}",
+                    MIN_ELO, MAX_ELO
+                );
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => self.set_option(tokens),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.engine.lock().unwrap().new_game();
+                self.position = Position::default();
+            }
+            Some("position") => self.set_position(tokens),
+            Some("go") => self.go(tokens),
+            Some("stop") => self.stop_search(),
+            Some("ponderhit") => self.stop.ponderhit(),
+            Some("quit") => return false,
+            _ => (),
+        }
+        true
+    }
+
+    /// Stops the running search, if any, and waits for its `bestmove`.
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.stop.stop();
+            let _ = search.join();
+        }
+    }
+
+    fn set_option<'a>(&mut self, tokens: impl Iterator<Item = &'a str>) {
+        let tokens: Vec<&str> = tokens.skip_while(|t| *t == "name").collect();
+        let (name, value) = match tokens.iter().position(|t| *t == "value") {
+            Some(split) => (tokens[..split].join(" "), tokens[split + 1..].join(" ")),
+            None => (tokens.join(" "), String::new()),
+        };
+        self.stop_search();
+        let mut engine = self.engine.lock().unwrap();
+        match (name.to_lowercase().as_str(), value.parse::<usize>()) {
+            ("threads", Ok(threads)) => engine.set_threads(threads.clamp(1, 256)),
+            ("hash", Ok(megabytes)) => engine.set_hash(megabytes.clamp(1, 4096)),
+            ("move overhead", Ok(millis)) => {
+                engine.move_overhead = Duration::from_millis(millis.min(5000) as u64)
+            }
+            ("multipv", Ok(lines)) => self.multi_pv = lines.clamp(1, 256),
+            // Only tells that the GUI may send `go ponder`, nothing to set up
+            ("ponder", _) => (),
+            ("skill level", Ok(level)) => {
+                self.skill_level = level.min(MAX_SKILL_LEVEL as usize) as u32
+            }
+            ("uci_limitstrength", _) => self.limit_strength = value == "true",
+            ("uci_elo", Ok(elo)) => self.elo = elo.clamp(MIN_ELO as usize, MAX_ELO as usize) as u32,
+            _ => println!("info string unknown option {}", name),
+        }
+        // UCI_LimitStrength takes precedence over the skill level
+        engine.skill = if self.limit_strength {
+            Skill::from_elo(self.elo)
+        } else {
+            Skill::level(self.skill_level)
+        };
+    }
+
+    fn set_position<'a>(&mut self, mut tokens: impl Iterator<Item = &'a str>) {
+        self.position = match tokens.next() {
+            Some("startpos") => Position::default(),
+            Some("fen") => {
+                let fen: Vec<&str> = tokens.by_ref().take_while(|t| *t != "moves").collect();
+                match Position::from_fen(&fen.join(" ")) {
+                    Ok(position) => position,
+                    Err(error) => {
+                        println!("info string {}", error);
+                        return;
+                    }
+                }
+            }
+            _ => return,
+        };
+
+        // Only after startpos is the `moves` keyword still ahead
+        for text in tokens.skip_while(|t| *t == "moves") {
+            match self.position.find_move(text) {
+                Some(mv) => self.position.make_move(&mv),
+                None => {
+                    println!("info string illegal move {}", text);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn go<'a>(&mut self, mut tokens: impl Iterator<Item = &'a str>) {
+        self.stop_search();
+
+        let mut limits = Limits {
+            multi_pv: self.multi_pv,
+            ..Limits::default()
+        };
+        while let Some(token) = tokens.next() {
+            if token == "infinite" {
+                limits.infinite = true;
+                continue;
+            }
+            if token == "ponder" {
+                limits.ponder = true;
+                continue;
+            }
+            let value = match tokens.next().and_then(|v| v.parse::<u64>().ok()) {
+                Some(value) => value,
+                None => continue,
+            };
+            let millis = Duration::from_millis(value);
+            match token {
+                "depth" => limits.depth = Some((value as i32).clamp(1, MAX_PLY as i32 - 1)),
+                "nodes" => limits.nodes = Some(value),
+                "mate" => limits.mate = Some(value as u32),
+                "movetime" => limits.move_time = Some(millis),
+                "wtime" => limits.clock.white_time = Some(millis),
+                "btime" => limits.clock.black_time = Some(millis),
+                "winc" => limits.clock.white_increment = millis,
+                "binc" => limits.clock.black_increment = millis,
+                "movestogo" => limits.clock.moves_to_go = Some(value as u32),
+                _ => (),
+            }
+        }
+        // A bare `go` would otherwise think until told to stop
+        if !limits.infinite
+            && limits.depth.is_none()
+            && limits.nodes.is_none()
+            && limits.mate.is_none()
+            && limits.move_time.is_none()
+            && !limits.clock.is_timed()
+        {
+            limits.depth = Some(DEFAULT_DEPTH);
+        }
+
+        let engine = self.engine.clone();
+        let mut position = self.position.clone();
+        let (started, on_start) = mpsc::channel();
+        self.search = Some(thread::spawn(move || {
+            let result =
+                engine
+                    .lock()
+                    .unwrap()
+                    .search(&mut position, limits, &UciObserver { started });
+            match (result.best_move, result.pv.get(1)) {
+                (Some(mv), Some(reply)) => println!("bestmove {} ponder {}", mv, reply),
+                (Some(mv), None) => println!("bestmove {}", mv),
+                (None, _) => println!("bestmove 0000"),
+            }
+        }));
+        // A `stop` or `ponderhit` read before the search started would be lost
+        let _ = on_start.recv();
+    }
+}
+
+/// Prints every iteration as an `info` line.
+struct UciObserver {
+    started: Sender<()>,
+}
+
+impl SearchObserver for UciObserver {
+    fn on_start(&self) {
+        let _ = self.started.send(());
+    }
+
+    fn on_iteration(&self, info: &SearchInfo) {
+        let pv: Vec<String> = info.pv.iter().map(|m| m.to_string()).collect();
+        println!(
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+            info.depth,
+            info.seldepth,
+            info.multipv,
+            format_score(info.score),
+            info.nodes,
+            info.nps,
+            info.hashfull,
+            info.time.as_millis(),
+            pv.join(" ")
+        );
+    }
+}
+
+/// Scores near `MATE` are reported as moves to mate rather than centipawns.
+fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skill(uci: &Uci) -> Skill {
+        uci.engine.lock().unwrap().skill
+    }
+
+    #[test]
+    fn limit_strength_gates_elo() {
+        let mut uci = Uci::default();
+        uci.handle("setoption name UCI_Elo value 1200");
+        assert_eq!(skill(&uci), Skill::default());
+        uci.handle("setoption name UCI_LimitStrength value true");
+        assert_eq!(skill(&uci), Skill::from_elo(1200));
+        assert!(skill(&uci).is_limited());
+        // The skill level only counts again once the Elo is off
+        uci.handle("setoption name Skill Level value 5");
+        assert_eq!(skill(&uci), Skill::from_elo(1200));
+        uci.handle("setoption name UCI_LimitStrength value false");
+        assert_eq!(skill(&uci), Skill::level(5));
+    }
+}
@@ -0,0 +1,397 @@
+use crate::board::PieceColor;
+use crate::engine::{Engine, SearchObserver, StopHandle};
+use crate::moves::Move;
+use crate::position::Position;
+use crate::search::{mate_in, Limits, SearchInfo, MAX_PLY};
+use crate::time::TimeControl;
+use crate::uci::DEFAULT_DEPTH;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Something the front end has to react to.
+enum Event {
+    /// A line sent by the GUI
+    Command(String),
+    /// The search with this id finished with this move
+    Done(u64, Option<Move>),
+}
+
+/// Time control set with `level`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Level {
+    /// Moves per session, 0 for the whole game
+    moves: u32,
+    increment: Duration,
+}
+
+/// Chess Engine Communication Protocol (xboard/WinBoard) front end reading
+/// commands from stdin.
+///
+/// Unlike UCI the engine keeps the game itself and answers with its own moves.
+/// Commands and finished searches come through one channel, so the engine's move
+/// is always played before the next command is looked at.
+pub struct XBoard {
+    position: Position,
+    engine: Arc<Mutex<Engine>>,
+    stop: StopHandle,
+    sender: Sender<Event>,
+    events: Receiver<Event>,
+    search: Option<JoinHandle<()>>,
+    // Id of the running search, results of older ones are dropped
+    search_id: u64,
+    /// Side the engine plays, `None` in force mode
+    engine_side: Option<PieceColor>,
+    post: bool,
+    level: Level,
+    time: Option<Duration>,
+    opponent_time: Option<Duration>,
+    move_time: Option<Duration>,
+    depth: Option<i32>,
+}
+
+impl Default for XBoard {
+    fn default() -> Self {
+        let engine = Engine::default();
+        let (sender, events) = mpsc::channel();
+        Self {
+            position: Position::default(),
+            stop: engine.stop_handle(),
+            engine: Arc::new(Mutex::new(engine)),
+            sender,
+            events,
+            search: None,
+            search_id: 0,
+            engine_side: Some(PieceColor::Black),
+            post: false,
+            level: Level::default(),
+            time: None,
+            opponent_time: None,
+            move_time: None,
+            depth: None,
+        }
+    }
+}
+
+impl XBoard {
+    pub fn run(&mut self) {
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if sender.send(Event::Command(line)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Event::Command("quit".to_string()));
+        });
+
+        while let Ok(event) = self.events.recv() {
+            match event {
+                Event::Command(line) => {
+                    if !self.handle(&line) {
+                        break;
+                    }
+                }
+                Event::Done(id, best_move) => self.play(id, best_move),
+            }
+        }
+        self.cancel_search();
+    }
+
+    /// Handles one command, returns false once the engine should exit.
+    fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        let command = match tokens.next() {
+            Some(command) => command,
+            None => return true,
+        };
+        let argument = tokens.next();
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" => (),
+            "protover" => println!(
+                "feature myname=\"YAUCHE {}\" ping=1 setboard=1 usermove=1 playother=0 \
+                 san=0 colors=0 sigint=0 sigterm=0 reuse=1 analyze=0 done=1",
+                env!("CARGO_PKG_VERSION")
+            ),
+            "ping" => println!("pong {}", argument.unwrap_or_default()),
+            "new" => {
+                self.cancel_search();
+                self.engine.lock().unwrap().new_game();
+                self.position = Position::default();
+                self.engine_side = Some(PieceColor::Black);
+                self.move_time = None;
+                self.depth = None;
+            }
+            "force" | "result" => {
+                self.cancel_search();
+                self.engine_side = None;
+            }
+            "go" => {
+                self.cancel_search();
+                self.engine_side = Some(self.position.side);
+                self.think();
+            }
+            "usermove" => self.user_move(argument.unwrap_or_default()),
+            // The base time is skipped, `time` brings the clock before every move
+            "level" => self.set_level(argument, tokens.nth(1)),
+            // `st` and `level` replace each other
+            "st" => {
+                self.move_time = argument
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .map(|s| Duration::from_secs_f64(s.max(0.0)));
+                self.level = Level::default();
+                self.time = None;
+                self.opponent_time = None;
+            }
+            "sd" => {
+                self.depth = argument
+                    .and_then(|d| d.parse::<i32>().ok())
+                    .map(|d| d.clamp(1, MAX_PLY as i32 - 1));
+            }
+            "time" => self.time = argument.and_then(centiseconds),
+            "otim" => self.opponent_time = argument.and_then(centiseconds),
+            "undo" => {
+                self.cancel_search();
+                if self.position.undo_move().is_err() {
+                    println!("Error (command not legal now): undo");
+                }
+            }
+            "remove" => {
+                self.cancel_search();
+                // Both plies or neither, so the engine keeps its colour
+                if self.position.log.len() < self.position.setup_moves + 2 {
+                    println!("Error (command not legal now): remove");
+                } else {
+                    for _ in 0..2 {
+                        let _ = self.position.undo_move();
+                    }
+                }
+            }
+            "setboard" => {
+                self.cancel_search();
+                let fen = line.trim_start().trim_start_matches("setboard");
+                match Position::from_fen(fen) {
+                    Ok(position) => self.position = position,
+                    Err(error) => println!("tellusererror Illegal position: {}", error),
+                }
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            // Move now
+            "?" => self.stop.stop(),
+            "quit" => return false,
+            _ => println!("Error (unknown command): {}", command),
+        }
+        true
+    }
+
+    fn user_move(&mut self, text: &str) {
+        self.cancel_search();
+        match self.position.find_move(text) {
+            Some(mv) => {
+                self.position.make_move(&mv);
+                self.think();
+            }
+            None => println!("Illegal move: {}", text),
+        }
+    }
+
+    /// Session length and increment in seconds from `level MPS BASE INC`.
+    fn set_level(&mut self, moves: Option<&str>, increment: Option<&str>) {
+        self.move_time = None;
+        self.level = Level {
+            moves: moves.and_then(|m| m.parse().ok()).unwrap_or(0),
+            increment: increment
+                .and_then(|i| i.parse::<f64>().ok())
+                .map(|i| Duration::from_secs_f64(i.max(0.0)))
+                .unwrap_or_default(),
+        };
+    }
+
+    /// Stops the running search, if any, without playing its move.
+    fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.stop.stop();
+            let _ = search.join();
+        }
+        self.search_id += 1;
+    }
+
+    /// Plays the move found by search `id` unless that search was cancelled.
+    fn play(&mut self, id: u64, best_move: Option<Move>) {
+        if id != self.search_id {
+            return;
+        }
+        if let Some(search) = self.search.take() {
+            let _ = search.join();
+        }
+        if let Some(mv) = best_move {
+            self.position.make_move(&mv);
+            println!("move {}", mv);
+            self.announce_result();
+        }
+    }
+
+    /// Prints the result if the side to move has no legal moves left.
+    fn announce_result(&self) -> bool {
+        let position = &self.position;
+        if !position
+            .board
+            .get_v_moves(&position.side, &position.log)
+            .is_empty()
+        {
+            return false;
+        }
+        match (position.board.in_check(&position.side), position.side) {
+            (true, PieceColor::Black) => println!("1-0 {{White mates}}"),
+            (true, PieceColor::White) => println!("0-1 {{Black mates}}"),
+            (false, _) => println!("1/2-1/2 {{Stalemate}}"),
+        }
+        true
+    }
+
+    /// Starts searching in the background if it is the engine's turn.
+    fn think(&mut self) {
+        if self.engine_side != Some(self.position.side) || self.announce_result() {
+            return;
+        }
+
+        let mut limits = Limits {
+            depth: self.depth,
+            move_time: self.move_time,
+            ..Limits::default()
+        };
+        if let (None, Some(time)) = (self.move_time, self.time) {
+            let opponent_time = self.opponent_time.unwrap_or(time);
+            let (white_time, black_time) = match self.position.side {
+                PieceColor::White => (time, opponent_time),
+                PieceColor::Black => (opponent_time, time),
+            };
+            let played = (self.position.log.len() - self.position.setup_moves) as u32 / 2;
+            limits.clock = TimeControl {
+                white_time: Some(white_time),
+                black_time: Some(black_time),
+                white_increment: self.level.increment,
+                black_increment: self.level.increment,
+                moves_to_go: (self.level.moves > 0)
+                    .then(|| self.level.moves - played % self.level.moves),
+            };
+        }
+        if limits.depth.is_none() && limits.move_time.is_none() && !limits.clock.is_timed() {
+            limits.depth = Some(DEFAULT_DEPTH);
+        }
+
+        self.search_id += 1;
+        let id = self.search_id;
+        let engine = self.engine.clone();
+        let mut position = self.position.clone();
+        let events = self.sender.clone();
+        let (started, on_start) = mpsc::channel();
+        let observer = XBoardObserver {
+            post: self.post,
+            started,
+        };
+        self.search = Some(thread::spawn(move || {
+            let result = engine
+                .lock()
+                .unwrap()
+                .search(&mut position, limits, &observer);
+            let _ = events.send(Event::Done(id, result.best_move));
+        }));
+        // A `?` read before the search started would be lost
+        let _ = on_start.recv();
+    }
+}
+
+fn centiseconds(text: &str) -> Option<Duration> {
+    text.parse::<u64>()
+        .ok()
+        .map(|cs| Duration::from_millis(cs * 10))
+}
+
+/// Prints the thinking output enabled with `post`.
+struct XBoardObserver {
+    post: bool,
+    started: Sender<()>,
+}
+
+impl SearchObserver for XBoardObserver {
+    fn on_start(&self) {
+        let _ = self.started.send(());
+    }
+
+    fn on_iteration(&self, info: &SearchInfo) {
+        if !self.post || info.multipv > 1 {
+            return;
+        }
+        let pv: Vec<String> = info.pv.iter().map(|m| m.to_string()).collect();
+        println!(
+            "{} {} {} {} {}",
+            info.depth,
+            format_score(info.score),
+            info.time.as_millis() / 10,
+            info.nodes,
+            pv.join(" ")
+        );
+    }
+}
+
+/// Mates are reported as 100000 plus the moves to mate, the xboard convention.
+fn format_score(score: i32) -> i32 {
+    match mate_in(score) {
+        Some(moves) if moves > 0 => 100_000 + moves,
+        Some(moves) => -100_000 + moves,
+        None => score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MATE;
+
+    #[test]
+    fn remove_takes_back_two_plies_or_none() {
+        let mut xboard = XBoard::default();
+        xboard.handle("force");
+        xboard.handle("usermove e2e4");
+        xboard.handle("remove");
+        assert_eq!(xboard.position.log.len(), 1);
+
+        xboard.handle("usermove e7e5");
+        xboard.handle("remove");
+        assert!(xboard.position.log.is_empty());
+        assert_eq!(xboard.position.side, PieceColor::White);
+    }
+
+    #[test]
+    fn st_and_level_replace_each_other() {
+        let mut xboard = XBoard::default();
+        xboard.handle("level 40 5 2");
+        xboard.handle("time 30000");
+        xboard.handle("st 3");
+        assert_eq!(xboard.move_time, Some(Duration::from_secs(3)));
+        assert_eq!(xboard.level.moves, 0);
+        assert_eq!(xboard.level.increment, Duration::ZERO);
+        assert_eq!(xboard.time, None);
+
+        xboard.handle("level 40 5 2");
+        assert_eq!(xboard.move_time, None);
+        assert_eq!(xboard.level.moves, 40);
+        assert_eq!(xboard.level.increment, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn mates_use_the_xboard_convention() {
+        assert_eq!(format_score(MATE - 1), 100_001);
+        assert_eq!(format_score(-MATE + 2), -100_001);
+        assert_eq!(format_score(-35), -35);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::board::{Board, Piece, PieceColor, PieceType, SIZE};
+use crate::moves::Move;
+
+// Pawns, rooks and kings get separate keys for their moved flags,
+// since those decide double pushes and castling
+const PIECE_KINDS: usize = 18;
+
+/// SplitMix64 step: returns the next state and the random number drawn from it.
+pub const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+static PIECE_KEYS: [[u64; SIZE]; PIECE_KINDS] = {
+    let mut keys = [[0; SIZE]; PIECE_KINDS];
+    let mut state = 0x5941_5543_4845;
+    let mut kind = 0;
+    while kind < PIECE_KINDS {
+        let mut square = 0;
+        while square < SIZE {
+            let (next, key) = split_mix(state);
+            state = next;
+            keys[kind][square] = key;
+            square += 1;
+        }
+        kind += 1;
+    }
+    keys
+};
+
+const EP_KEYS: [u64; 10] = {
+    let mut keys = [0; 10];
+    let mut state = 0x4550;
+    let mut file = 0;
+    while file < 10 {
+        let (next, key) = split_mix(state);
+        state = next;
+        keys[file] = key;
+        file += 1;
+    }
+    keys
+};
+
+const BLACK_TO_MOVE: u64 = split_mix(0x0042_4C41_434B).1;
+
+/// Key of `piece` standing on `square`.
+pub(crate) fn piece_key(piece: &Piece, square: usize) -> u64 {
+    PIECE_KEYS[piece_kind(piece)][square]
+}
+
+fn piece_kind(piece: &Piece) -> usize {
+    let kind = match piece.piece_type {
+        PieceType::Pawn(false) => 0,
+        PieceType::Pawn(true) => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook(false) => 4,
+        PieceType::Rook(true) => 5,
+        PieceType::Queen => 6,
+        PieceType::King(false) => 7,
+        PieceType::King(true) => 8,
+    };
+    match piece.piece_color {
+        PieceColor::White => kind,
+        PieceColor::Black => kind + PIECE_KINDS / 2,
+    }
+}
+
+impl Board {
+    /// Zobrist key of the position with `color` to move. The last move in `log`
+    /// contributes an en passant key when it was a double pawn push. The piece
+    /// keys are kept up to date by [`Board::set`], so this is cheap.
+    pub fn hash(&self, color: &PieceColor, log: &[Move]) -> u64 {
+        let mut hash = self.piece_keys();
+        if *color == PieceColor::Black {
+            hash ^= BLACK_TO_MOVE;
+        }
+        if let Some(last) = log.last() {
+            let pawn_moved = matches!(
+                self.fields()[last.target],
+                Some(Piece {
+                    piece_type: PieceType::Pawn(_),
+                    ..
+                })
+            );
+            if pawn_moved && last.source.abs_diff(last.target) == 20 {
+                hash ^= EP_KEYS[last.target % 10];
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    // The piece keys recomputed from scratch
+    fn scanned(board: &Board) -> u64 {
+        board
+            .fields()
+            .iter()
+            .enumerate()
+            .filter_map(|(square, piece)| piece.map(|piece| piece_key(&piece, square)))
+            .fold(0, |key, piece| key ^ piece)
+    }
+
+    #[test]
+    fn key_follows_moves_and_undo() {
+        let fen = "r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1";
+        let mut position = Position::from_fen(fen).unwrap();
+        let start = position.board.hash(&position.side, &position.log);
+        // Castling, captures, promotions and en passant along the way
+        for text in ["exd6", "O-O", "bxa8=Q", "Rxa8", "O-O-O", "gxh1=Q"] {
+            let mv = position.parse_move(text).unwrap();
+            position.make_move(&mv);
+            assert_eq!(
+                position.board.piece_keys(),
+                scanned(&position.board),
+                "{}",
+                text
+            );
+        }
+        while position.undo_move().is_ok() {
+            assert_eq!(position.board.piece_keys(), scanned(&position.board));
+        }
+        assert_eq!(position.board.hash(&position.side, &position.log), start);
+    }
+
+    #[test]
+    fn transpositions_share_a_key() {
+        let mut a = Position::default();
+        let mut b = Position::default();
+        for text in ["e3", "e6", "Nf3", "Nc6"] {
+            let mv = a.parse_move(text).unwrap();
+            a.make_move(&mv);
+        }
+        for text in ["Nf3", "Nc6", "e3", "e6"] {
+            let mv = b.parse_move(text).unwrap();
+            b.make_move(&mv);
+        }
+        assert_eq!(a.board.hash(&a.side, &a.log), b.board.hash(&b.side, &b.log));
+        assert_ne!(
+            a.board.hash(&a.side, &a.log),
+            Position::default().board.hash(&PieceColor::White, &[])
+        );
+    }
+}